@@ -0,0 +1,362 @@
+use std::fmt::Write;
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+
+use crate::chat::ChatMessage;
+use crate::clock::SimulatedClock;
+use crate::floor::Floor;
+use crate::pool::ThreadPool;
+use crate::queues::{self, LockedReceiver, LockedSender, RingReceiver, RingSender};
+use crate::scenario::Scenario;
+use crate::session::{self, Execution};
+
+// Runs whole sessions of the default scenario, with nobody taking any time over anything, in simulated time so
+// that nothing ever sleeps, once for every way of running students. As that gets slower with the square of the
+// class size, it is only done for classes of up to SESSION_MAX_STUDENTS.
+//
+// Then takes the paths busiest in a crowded session, the chat and the floor, out of the simulation and runs them
+// flat out for classes of any size: students are jobs on a thread pool, chatting or speaking up as fast as they
+// can. The chat path is run through every ChatPath, the floor through the one Floor.
+pub(crate) struct BenchConfig {
+    pub(crate) class_sizes: Vec<usize>,
+    // chat messages every student sends
+    pub(crate) messages: usize,
+    // on the pool, and threads of the executor, when running sessions too
+    pub(crate) workers: usize,
+}
+
+// How one path did with one class size
+pub(crate) struct BenchResult {
+    path: &'static str,
+    implementation: String,
+    students: usize,
+    // events in the transcript, messages received, or turns taken at speaking
+    operations: usize,
+    elapsed: Duration,
+    // from sending to receiving, or from raising a hand to being given the floor, as sessions take no time at all
+    latency: Option<Latency>,
+    // most messages sent but not received yet at any time
+    peak_backlog: Option<usize>,
+    // of the whole process, as seen by the chat screen every so often, where the OS tells
+    peak_rss_kb: Option<u64>,
+}
+
+struct Latency {
+    mean: Duration,
+    p99: Duration,
+    max: Duration,
+}
+
+// How the chat screen checks on memory use
+const RSS_SAMPLE_EVERY: usize = 1024;
+// How many messages fit in the ring buffer before students have to wait for the chat screen to catch up
+const RING_CAPACITY: usize = 4096;
+// Long enough for nobody ever to be cut off
+const MAX_SPEAKING_TIME: Duration = Duration::from_secs(3600);
+// A thread per student takes the best part of a minute with this many
+const SESSION_MAX_STUDENTS: usize = 1_000;
+
+type Timed = (Instant, ChatMessage);
+
+// From every student to the chat screen
+trait ChatPath {
+    const NAME: &'static str;
+    type Sender: Clone + Send + 'static;
+    type Receiver: Send + 'static;
+
+    fn open() -> (Self::Sender, Self::Receiver);
+    fn send(sender: &Self::Sender, message: Timed);
+    // None once every sender is gone and every message is out
+    fn recv(receiver: &mut Self::Receiver) -> Option<Timed>;
+}
+
+struct StdMpsc;
+struct LockedQueue;
+struct RingBuffer;
+
+impl ChatPath for StdMpsc {
+    const NAME: &'static str = "std mpsc";
+    type Sender = Sender<Timed>;
+    type Receiver = Receiver<Timed>;
+
+    fn open() -> (Self::Sender, Self::Receiver) {
+        mpsc::channel()
+    }
+
+    fn send(sender: &Self::Sender, message: Timed) {
+        sender.send(message).unwrap();
+    }
+
+    fn recv(receiver: &mut Self::Receiver) -> Option<Timed> {
+        receiver.recv().ok()
+    }
+}
+
+impl ChatPath for LockedQueue {
+    const NAME: &'static str = "mutex + condvar";
+    type Sender = LockedSender<Timed>;
+    type Receiver = LockedReceiver<Timed>;
+
+    fn open() -> (Self::Sender, Self::Receiver) {
+        queues::locked_channel()
+    }
+
+    fn send(sender: &Self::Sender, message: Timed) {
+        sender.send(message);
+    }
+
+    fn recv(receiver: &mut Self::Receiver) -> Option<Timed> {
+        receiver.recv()
+    }
+}
+
+impl ChatPath for RingBuffer {
+    const NAME: &'static str = "lock-free ring";
+    type Sender = RingSender<Timed>;
+    type Receiver = RingReceiver<Timed>;
+
+    fn open() -> (Self::Sender, Self::Receiver) {
+        queues::ring_channel(RING_CAPACITY)
+    }
+
+    fn send(sender: &Self::Sender, message: Timed) {
+        sender.send(message);
+    }
+
+    fn recv(receiver: &mut Self::Receiver) -> Option<Timed> {
+        receiver.recv()
+    }
+}
+
+// Every path, every class size, smallest first
+pub(crate) fn run(config: &BenchConfig) -> Vec<BenchResult> {
+    let mut results = vec![];
+    for &students in &config.class_sizes {
+        if students <= SESSION_MAX_STUDENTS {
+            let executions = [Execution::ThreadPerStudent, Execution::Pool { workers: config.workers },
+                              Execution::Async { threads: 1 }, Execution::Async { threads: config.workers }];
+            for execution in executions.iter() {
+                results.push(bench_session(students, *execution));
+            }
+        }
+        results.push(bench_chat::<StdMpsc>(students, config.messages, config.workers));
+        results.push(bench_chat::<LockedQueue>(students, config.messages, config.workers));
+        results.push(bench_chat::<RingBuffer>(students, config.messages, config.workers));
+        results.push(bench_floor(students, config.workers));
+    }
+    results
+}
+
+fn bench_session(students: usize, execution: Execution) -> BenchResult {
+    let mut scenario = Scenario::default().with_class_size(students);
+    scenario.presenter.pace_ms = 0;
+    scenario.presenter.answer_ms = 0;
+    scenario.students.iter_mut().for_each(|profile| profile.pace_ms = 0);
+
+    let started = Instant::now();
+    let record = session::run_session(scenario, Arc::new(SimulatedClock::starting_at_epoch()), execution);
+    let elapsed = started.elapsed();
+
+    BenchResult {
+        path: "session",
+        implementation: execution.to_string(),
+        students,
+        operations: record.transcript.events.len(),
+        elapsed,
+        latency: None,
+        peak_backlog: None,
+        // with the whole transcript still around
+        peak_rss_kb: resident_kb(),
+    }
+}
+
+fn bench_chat<P: ChatPath>(students: usize, messages: usize, workers: usize) -> BenchResult {
+    let (sender, mut receiver) = P::open();
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let peak_backlog = Arc::new(AtomicUsize::new(0));
+    let screen = {
+        let in_flight = Arc::clone(&in_flight);
+        thread::spawn(move || {
+            let mut latencies = Vec::with_capacity(students * messages);
+            let mut peak_rss_kb = resident_kb();
+            while let Some((sent, _message)) = P::recv(&mut receiver) {
+                latencies.push(sent.elapsed());
+                in_flight.fetch_sub(1, Ordering::Relaxed);
+                if latencies.len() % RSS_SAMPLE_EVERY == 0 {
+                    peak_rss_kb = peak_rss_kb.max(resident_kb());
+                }
+            }
+            (latencies, peak_rss_kb.max(resident_kb()))
+        })
+    };
+
+    let started = Instant::now();
+    let pool = ThreadPool::new(workers);
+    for student in 0..students {
+        let (sender, in_flight, peak_backlog) = (sender.clone(), Arc::clone(&in_flight), Arc::clone(&peak_backlog));
+        pool.execute(move || {
+            let name = format!("Student_{}", student + 1);
+            for message in 0..messages {
+                let message = ChatMessage::new(&name, &format!("Message {}", message + 1), Utc::now());
+                let backlog = in_flight.fetch_add(1, Ordering::Relaxed) + 1;
+                peak_backlog.fetch_max(backlog, Ordering::Relaxed);
+                P::send(&sender, (Instant::now(), message));
+            }
+        });
+    }
+    drop(sender);
+    drop(pool);
+    let (latencies, peak_rss_kb) = screen.join().unwrap();
+    let elapsed = started.elapsed();
+    assert_eq!(latencies.len(), students * messages, "every message sent is received");
+
+    BenchResult {
+        path: "chat",
+        implementation: String::from(P::NAME),
+        students,
+        operations: latencies.len(),
+        elapsed,
+        latency: Some(latency(latencies)),
+        peak_backlog: Some(peak_backlog.load(Ordering::Relaxed)),
+        peak_rss_kb,
+    }
+}
+
+// Every student raises their hand once, and speaks for no time at all once given the floor
+fn bench_floor(students: usize, workers: usize) -> BenchResult {
+    let floor = Arc::new(Floor::new("Presenter", MAX_SPEAKING_TIME));
+    let (waits_tx, waits_rx) = mpsc::channel();
+
+    let started = Instant::now();
+    let pool = ThreadPool::new(workers);
+    for student in 0..students {
+        let (floor, waits_tx) = (Arc::clone(&floor), waits_tx.clone());
+        pool.execute(move || {
+            let name = format!("Student_{}", student + 1);
+            let raised = Instant::now();
+            floor.raise_hand(&name);
+            // with the sleeps taken out, waiting for the floor is spinning
+            while !floor.try_take(&name, Utc::now()) {
+                thread::yield_now();
+            }
+            let waited = raised.elapsed();
+            floor.release(&name, Utc::now());
+            waits_tx.send(waited).unwrap();
+        });
+    }
+    drop(waits_tx);
+    drop(pool);
+    let elapsed = started.elapsed();
+    let waits: Vec<_> = waits_rx.iter().collect();
+    assert_eq!(waits.len(), students, "every student gets to speak");
+
+    BenchResult {
+        path: "floor",
+        implementation: String::from("mutex"),
+        students,
+        operations: waits.len(),
+        elapsed,
+        latency: Some(latency(waits)),
+        peak_backlog: None,
+        peak_rss_kb: None,
+    }
+}
+
+fn latency(mut samples: Vec<Duration>) -> Latency {
+    samples.sort_unstable();
+    let mean = samples.iter().sum::<Duration>() / samples.len().max(1) as u32;
+    let p99 = samples.get(samples.len() * 99 / 100).or_else(|| samples.last()).copied().unwrap_or_default();
+    let max = samples.last().copied().unwrap_or_default();
+    Latency { mean, p99, max }
+}
+
+// Resident memory in kB, on Linux anyway
+fn resident_kb() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+impl BenchResult {
+    fn throughput(&self) -> f64 {
+        self.operations as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+fn micros(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1_000_000.0
+}
+
+fn or_blank<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+// Mean, p99 and max, in µs, blank for sessions
+fn latency_us(latency: &Option<Latency>) -> [String; 3] {
+    match latency {
+        Some(latency) => [latency.mean, latency.p99, latency.max].map(|duration| format!("{:.1}", micros(duration))),
+        None => Default::default(),
+    }
+}
+
+pub(crate) fn report(results: &[BenchResult]) -> String {
+    let mut report = String::new();
+    // writing to a String never fails
+    writeln!(report, "{:<8} {:<22} {:>8} {:>10} {:>10} {:>12} {:>10} {:>10} {:>10} {:>8} {:>10}", "path",
+             "implementation", "students", "operations", "time (ms)", "per second", "mean (µs)", "p99 (µs)",
+             "max (µs)", "backlog", "RSS (kB)").unwrap();
+    for r in results {
+        let [mean, p99, max] = latency_us(&r.latency);
+        writeln!(report, "{:<8} {:<22} {:>8} {:>10} {:>10.1} {:>12.0} {:>10} {:>10} {:>10} {:>8} {:>10}", r.path,
+                 r.implementation, r.students, r.operations, r.elapsed.as_secs_f64() * 1000.0, r.throughput(), mean,
+                 p99, max, or_blank(r.peak_backlog), or_blank(r.peak_rss_kb)).unwrap();
+    }
+    report
+}
+
+// One row per path and class size, with a header, for plotting
+pub(crate) fn csv(results: &[BenchResult]) -> String {
+    let mut csv = String::from("path,implementation,students,operations,elapsed_ms,throughput_per_s,mean_us,p99_us,\
+                                max_us,peak_backlog,peak_rss_kb\n");
+    for r in results {
+        let [mean, p99, max] = latency_us(&r.latency);
+        writeln!(csv, "{},{},{},{},{:.3},{:.0},{},{},{},{},{}", r.path, r.implementation, r.students, r.operations,
+                 r.elapsed.as_secs_f64() * 1000.0, r.throughput(), mean, p99, max, or_blank(r.peak_backlog),
+                 or_blank(r.peak_rss_kb)).unwrap();
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_path_is_run_for_every_class_size() {
+        let config = BenchConfig { class_sizes: vec![3, 20], messages: 5, workers: 2 };
+        let results = run(&config);
+        let runs: Vec<_> = results.iter().map(|r| (r.path, r.implementation.as_str(), r.students)).collect();
+        let sessions = |students| vec![
+            ("session", "a thread per student", students), ("session", "a pool of 2 workers", students),
+            ("session", "async on 1 thread", students), ("session", "async on 2 threads", students),
+        ];
+        let paths = |students| vec![
+            ("chat", "std mpsc", students), ("chat", "mutex + condvar", students), ("chat", "lock-free ring", students),
+            ("floor", "mutex", students),
+        ];
+        assert_eq!(runs, [sessions(3), paths(3), sessions(20), paths(20)].concat());
+        // every student joins and leaves at least
+        assert!(results.iter().filter(|r| r.path == "session").all(|r| r.operations > r.students * 2));
+        let operations: Vec<_> = results.iter().filter(|r| r.path != "session").map(|r| r.operations).collect();
+        assert_eq!(operations, vec![15, 15, 15, 3, 100, 100, 100, 20]);
+        let csv = csv(&results);
+        assert_eq!(csv.lines().count(), 17);
+        assert!(csv.lines().all(|line| line.split(',').count() == 11));
+    }
+}
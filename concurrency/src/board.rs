@@ -0,0 +1,167 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+use crate::clock::{ActorId, Clock};
+
+// Keeps presenter and students in step: the presenter announces every concept on the board, students wait for
+// the announcement before listening, and let the presenter know when they are done reacting to it.
+//
+// Waiting is done by parking on the clock, which wakes everybody up whenever something changes on the board.
+// Tasks on an executor cannot park, so they leave a waker instead, woken at the same time.
+pub(crate) struct ConceptBoard {
+    clock: Arc<dyn Clock>,
+    students: usize,
+    state: Mutex<BoardState>,
+}
+
+// What participants wait for on the board
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Cue {
+    Announced(usize),
+    // every student has reacted to the concept
+    Reacted(usize),
+    // the class has been sent to breakout rooms, which may be closed again by now
+    RoomsOpened,
+}
+
+struct BoardState {
+    // concepts are announced in order, so this is all that is needed
+    announced: usize,
+    // how many students have reacted to every concept
+    reactions: Vec<usize>,
+    rooms_opened: bool,
+    wakers: Vec<Waker>,
+}
+
+impl ConceptBoard {
+    pub(crate) fn new(clock: Arc<dyn Clock>, concepts: usize, students: usize) -> Self {
+        Self {
+            clock,
+            students,
+            state: Mutex::new(BoardState {
+                announced: 0,
+                reactions: vec![0; concepts],
+                rooms_opened: false,
+                wakers: vec![],
+            }),
+        }
+    }
+
+    pub(crate) fn announce(&self, concept: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.announced = concept + 1;
+        self.changed(state);
+    }
+
+    pub(crate) fn reacted(&self, concept: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.reactions[concept] += 1;
+        self.changed(state);
+    }
+
+    pub(crate) fn open_rooms(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.rooms_opened = true;
+        self.changed(state);
+    }
+
+    pub(crate) fn is_given(&self, cue: Cue) -> bool {
+        self.given(&self.state.lock().unwrap(), cue)
+    }
+
+    pub(crate) fn wait_for(&self, actor: ActorId, cue: Cue) {
+        self.clock.park(actor, &|| self.is_given(cue));
+    }
+
+    // The async way of waiting for a cue
+    pub(crate) fn poll_for(&self, cue: Cue, cx: &mut Context) -> Poll<()> {
+        let mut state = self.state.lock().unwrap();
+        if self.given(&state, cue) {
+            Poll::Ready(())
+        } else {
+            state.wakers.push(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    fn given(&self, state: &BoardState, cue: Cue) -> bool {
+        match cue {
+            Cue::Announced(concept) => state.announced > concept,
+            Cue::Reacted(concept) => state.reactions[concept] == self.students,
+            Cue::RoomsOpened => state.rooms_opened,
+        }
+    }
+
+    // Wakes up everybody in the order they started waiting
+    fn changed(&self, mut state: MutexGuard<BoardState>) {
+        let wakers = std::mem::take(&mut state.wakers);
+        drop(state);
+        self.clock.unpark_all();
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::clock::{RealClock, SimulatedClock};
+
+    use super::*;
+
+    // The student is slower to react than the presenter is to present, but never ahead of the presenter
+    fn run_lesson(clock: Arc<dyn Clock>, wait_for_reactions: bool) -> Vec<String> {
+        let board = Arc::new(ConceptBoard::new(Arc::clone(&clock), 2, 1));
+        let log = Arc::new(Mutex::new(vec![]));
+        let presenter = clock.register_actor();
+        let student = clock.register_actor();
+        let presenter_handle = {
+            let (clock, board, log) = (Arc::clone(&clock), Arc::clone(&board), Arc::clone(&log));
+            thread::spawn(move || {
+                clock.start(presenter);
+                for concept in 0..2 {
+                    clock.sleep(presenter, Duration::from_millis(10));
+                    log.lock().unwrap().push(format!("presented {}", concept));
+                    board.announce(concept);
+                    if wait_for_reactions {
+                        board.wait_for(presenter, Cue::Reacted(concept));
+                    }
+                }
+                clock.finish(presenter);
+            })
+        };
+        let student_handle = {
+            let (clock, board, log) = (Arc::clone(&clock), Arc::clone(&board), Arc::clone(&log));
+            thread::spawn(move || {
+                clock.start(student);
+                for concept in 0..2 {
+                    board.wait_for(student, Cue::Announced(concept));
+                    clock.sleep(student, Duration::from_millis(15));
+                    log.lock().unwrap().push(format!("reacted to {}", concept));
+                    board.reacted(concept);
+                }
+                clock.finish(student);
+            })
+        };
+        presenter_handle.join().unwrap();
+        student_handle.join().unwrap();
+        let log = log.lock().unwrap().clone();
+        log
+    }
+
+    #[test]
+    fn students_never_get_ahead_of_the_presenter() {
+        let log = run_lesson(Arc::new(SimulatedClock::starting_at_epoch()), false);
+        assert_eq!(log, vec!["presented 0", "presented 1", "reacted to 0", "reacted to 1"]);
+    }
+
+    #[test]
+    fn the_presenter_can_wait_for_every_reaction() {
+        let expected = vec!["presented 0", "reacted to 0", "presented 1", "reacted to 1"];
+        assert_eq!(run_lesson(Arc::new(SimulatedClock::starting_at_epoch()), true), expected);
+        assert_eq!(run_lesson(Arc::new(RealClock::default()), true), expected);
+    }
+}
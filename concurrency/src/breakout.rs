@@ -0,0 +1,140 @@
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::chat::ChatMessage;
+use crate::moderation::ChatPipeline;
+use crate::scenario::{BreakoutConfig, FilterConfig};
+
+// Breakout rooms, for the class to talk things over in small groups in the middle of the session.
+//
+// Rooms only exist while they are open: opening them splits the class between them, and gives every room a chat
+// channel and a screen of its own, on a thread of its own, moderated like the main chat. Students only ever
+// borrow their room's sender for as long as it takes to say something, so that closing the rooms drops every
+// sender, which lets every screen finish and hand back the room's conversation.
+pub(crate) struct Breakout {
+    config: Option<BreakoutConfig>,
+    // everybody who goes to a room, in order
+    class: Vec<String>,
+    filters: FilterConfig,
+    rooms: Mutex<Vec<Room>>,
+}
+
+struct Room {
+    members: Vec<String>,
+    chat: Sender<ChatMessage>,
+    // everything it showed, once the chat is closed
+    screen: JoinHandle<Vec<ChatMessage>>,
+}
+
+impl Breakout {
+    // Without a config, the class never breaks out
+    pub(crate) fn new(config: Option<BreakoutConfig>, class: Vec<String>, filters: FilterConfig) -> Self {
+        Self {
+            config,
+            class,
+            filters,
+            rooms: Mutex::new(vec![]),
+        }
+    }
+
+    // Whether the class breaks out once done with that many concepts
+    pub(crate) fn is_after(&self, concepts: usize) -> bool {
+        self.config.as_ref().is_some_and(|config| config.after_concept == concepts)
+    }
+
+    pub(crate) fn duration(&self) -> Duration {
+        self.config.as_ref().map_or(Duration::ZERO, BreakoutConfig::duration)
+    }
+
+    // Sends everybody to a room, one after the other, returning who is in which. There are never more rooms than
+    // students, so that none is empty.
+    pub(crate) fn open(&self) -> Vec<Vec<String>> {
+        let count = self.config.as_ref().map_or(0, |config| config.rooms).min(self.class.len());
+        let mut members = vec![vec![]; count];
+        for (idx, name) in self.class.iter().enumerate() {
+            members[idx % count].push(name.clone());
+        }
+        let mut rooms = self.rooms.lock().unwrap();
+        for (idx, members) in members.iter().enumerate() {
+            let (chat, messages) = mpsc::channel::<ChatMessage>();
+            let mut pipeline = ChatPipeline::new(&self.filters);
+            let number = idx + 1;
+            let screen = thread::spawn(move || {
+                let tid = thread::current().id();
+                let mut conversation = vec![];
+                for msg in messages {
+                    match pipeline.process(msg) {
+                        Some(shown) => {
+                            println!("{:?} - Room {} screen received: {:?}", tid, number, shown);
+                            conversation.push(shown);
+                        }
+                        None => println!("{:?} - Room {} screen held back a message", tid, number),
+                    }
+                }
+                println!("{:?}: Room {} is closed, {} messages received", tid, number, conversation.len());
+                conversation
+            });
+            rooms.push(Room { members: members.clone(), chat, screen });
+        }
+        members
+    }
+
+    // Says something in the room of whoever sent it, unless there is no room for them, or no room open at all
+    pub(crate) fn say(&self, msg: ChatMessage) -> bool {
+        let rooms = self.rooms.lock().unwrap();
+        match rooms.iter().find(|room| room.members.contains(&msg.username)) {
+            // the screen only goes once the room is closed
+            Some(room) => room.chat.send(msg).is_ok(),
+            None => false,
+        }
+    }
+
+    // Brings everybody back, returning every room's members and conversation, in order, once their screens are
+    // done with them. Closing rooms that are not open does nothing.
+    pub(crate) fn close(&self) -> Vec<(Vec<String>, Vec<ChatMessage>)> {
+        let rooms = std::mem::take(&mut *self.rooms.lock().unwrap());
+        rooms.into_iter().map(|Room { members, chat, screen }| {
+            drop(chat);
+            (members, screen.join().unwrap())
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use super::*;
+
+    fn at(ms: i64) -> DateTime<Utc> {
+        Utc.ymd(2020, 1, 1).and_hms(9, 0, 0) + chrono::Duration::milliseconds(ms)
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| String::from(*name)).collect()
+    }
+
+    #[test]
+    fn everybody_only_talks_to_their_own_room_and_only_while_it_is_open() {
+        let config = BreakoutConfig { after_concept: 1, rooms: 4, duration_ms: 1000 };
+        let breakout = Breakout::new(Some(config), names(&["Ana", "Bob", "Cid"]), FilterConfig::default());
+        assert!(breakout.is_after(1) && !breakout.is_after(2));
+        assert!(!breakout.say(ChatMessage::new("Ana", "anybody?", at(0))));
+
+        assert_eq!(breakout.open(), vec![names(&["Ana"]), names(&["Bob"]), names(&["Cid"])]);
+        assert!(breakout.say(ChatMessage::new("Ana", "hi", at(0))));
+        assert!(breakout.say(ChatMessage::new("Cid", "hey", at(10))));
+        assert!(breakout.say(ChatMessage::new("Ana", "bye", at(20))));
+        assert!(!breakout.say(ChatMessage::new("Zoe", "me too", at(30))));
+
+        let closed = breakout.close();
+        let conversations: Vec<Vec<&str>> = closed.iter()
+            .map(|(_, conversation)| conversation.iter().map(|msg| msg.text.as_str()).collect())
+            .collect();
+        assert_eq!(conversations, vec![vec!["hi", "bye"], vec![], vec!["hey"]]);
+        assert!(!breakout.say(ChatMessage::new("Ana", "still there?", at(40))));
+        assert!(breakout.close().is_empty());
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, Weak};
+
+// A publish/subscribe channel: everything published reaches every subscriber.
+//
+// Every subscriber has its own bounded queue, so a slow subscriber never holds back the publisher or the other
+// subscribers. When its queue is full the oldest item is dropped, and the subscriber is told how many it missed
+// the next time it receives. Once every publisher is gone subscribers get what is left, and then Closed.
+// Receiving never blocks, subscribers check for news whenever it suits them.
+pub(crate) struct Publisher<T> {
+    shared: Arc<Shared<T>>,
+}
+
+pub(crate) struct Subscriber<T> {
+    inbox: Arc<Inbox<T>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum TryRecvError {
+    Empty,
+    // this many items were dropped since the last receive, because the subscriber could not keep up
+    Lagged(u64),
+    Closed,
+}
+
+struct Shared<T> {
+    state: Mutex<SharedState<T>>,
+}
+
+struct SharedState<T> {
+    subscribers: Vec<Weak<Inbox<T>>>,
+    publishers: usize,
+}
+
+struct Inbox<T> {
+    queue: Mutex<Queue<T>>,
+}
+
+struct Queue<T> {
+    items: VecDeque<T>,
+    capacity: usize,
+    missed: u64,
+    closed: bool,
+}
+
+pub(crate) fn channel<T: Clone>() -> Publisher<T> {
+    Publisher {
+        shared: Arc::new(Shared {
+            state: Mutex::new(SharedState {
+                subscribers: vec![],
+                publishers: 1,
+            }),
+        }),
+    }
+}
+
+impl<T: Clone> Publisher<T> {
+    // Only what is published from now on reaches the new subscriber
+    pub(crate) fn subscribe(&self, capacity: usize) -> Subscriber<T> {
+        assert!(capacity > 0, "a subscriber needs room for at least one item");
+        let inbox = Arc::new(Inbox {
+            queue: Mutex::new(Queue {
+                items: VecDeque::with_capacity(capacity),
+                capacity,
+                missed: 0,
+                closed: false,
+            }),
+        });
+        self.shared.state.lock().unwrap().subscribers.push(Arc::downgrade(&inbox));
+        Subscriber {
+            inbox,
+        }
+    }
+
+    // Returns how many subscribers got the item
+    pub(crate) fn publish(&self, item: T) -> usize {
+        let mut state = self.shared.state.lock().unwrap();
+        // forget subscribers that have been dropped
+        state.subscribers.retain(|subscriber| subscriber.strong_count() > 0);
+        let mut delivered = 0;
+        for subscriber in state.subscribers.iter().filter_map(Weak::upgrade) {
+            let mut queue = subscriber.queue.lock().unwrap();
+            if queue.items.len() == queue.capacity {
+                queue.items.pop_front();
+                queue.missed += 1;
+            }
+            queue.items.push_back(item.clone());
+            delivered += 1;
+        }
+        delivered
+    }
+}
+
+impl<T> Clone for Publisher<T> {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().unwrap().publishers += 1;
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Publisher<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.publishers -= 1;
+        if state.publishers == 0 {
+            for subscriber in state.subscribers.iter().filter_map(Weak::upgrade) {
+                subscriber.queue.lock().unwrap().closed = true;
+            }
+        }
+    }
+}
+
+impl<T> Subscriber<T> {
+    pub(crate) fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut queue = self.inbox.queue.lock().unwrap();
+        // missing items are reported before anything else, so the subscriber knows there is a gap
+        if queue.missed > 0 {
+            let missed = queue.missed;
+            queue.missed = 0;
+            return Err(TryRecvError::Lagged(missed));
+        }
+        match queue.items.pop_front() {
+            Some(item) => Ok(item),
+            None if queue.closed => Err(TryRecvError::Closed),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    fn receive_all<T>(subscriber: &Subscriber<T>) -> Vec<T> {
+        let mut received = vec![];
+        while let Ok(item) = subscriber.try_recv() {
+            received.push(item);
+        }
+        received
+    }
+
+    #[test]
+    fn every_subscriber_gets_every_item() {
+        let publisher = channel();
+        let subscribers: Vec<Subscriber<i32>> = (0..3).map(|_| publisher.subscribe(10)).collect();
+        // from another thread, as it would be used
+        thread::spawn(move || {
+            for i in 0..5 {
+                assert_eq!(publisher.publish(i), 3);
+            }
+        }).join().unwrap();
+        for subscriber in subscribers {
+            assert_eq!(receive_all(&subscriber), vec![0, 1, 2, 3, 4]);
+            assert_eq!(subscriber.try_recv(), Err(TryRecvError::Closed));
+        }
+    }
+
+    #[test]
+    fn slow_subscribers_lag_without_holding_back_the_others() {
+        let publisher = channel();
+        let slow = publisher.subscribe(2);
+        let fast = publisher.subscribe(10);
+        for i in 0..5 {
+            publisher.publish(i);
+        }
+        assert_eq!(slow.try_recv(), Err(TryRecvError::Lagged(3)));
+        assert_eq!(slow.try_recv(), Ok(3));
+        assert_eq!(slow.try_recv(), Ok(4));
+        assert_eq!(slow.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(receive_all(&fast), vec![0, 1, 2, 3, 4]);
+        assert_eq!(fast.try_recv(), Err(TryRecvError::Empty));
+        drop(publisher);
+        assert_eq!(fast.try_recv(), Err(TryRecvError::Closed));
+    }
+
+    #[test]
+    fn dropped_subscribers_are_forgotten() {
+        let publisher = channel();
+        let kept = publisher.subscribe(1);
+        drop(publisher.subscribe(1));
+        assert_eq!(publisher.publish("hello"), 1);
+        assert_eq!(kept.try_recv(), Ok("hello"));
+    }
+}
@@ -0,0 +1,270 @@
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+
+use futures::channel::mpsc as async_mpsc;
+use futures::channel::mpsc::{UnboundedReceiver, UnboundedSender};
+use futures::StreamExt;
+
+use crate::broadcast::{Subscriber, TryRecvError};
+use crate::events::Event;
+use crate::queues;
+use crate::queues::{BoundedReceiver, BoundedSender, Overflow, OverflowCounts};
+
+// Anything built out of the event stream: screens, transcripts, reports...
+pub(crate) trait EventConsumer: Send {
+    fn handle(&mut self, event: &Event);
+
+    // Called once every event has been handled
+    fn finish(&mut self) {}
+}
+
+// The center of the event bus.
+//
+// Participants send their events through the EventSender they get with the hub, and the hub hands every one of
+// them, in the order they arrived, to each of its consumers. The hub runs on a thread of its own, or (with an
+// async receiver) as a task on an executor.
+pub(crate) struct EventHub<R> {
+    rx: R,
+    consumers: Vec<Box<dyn EventConsumer>>,
+    events: Vec<Event>,
+    progress: Option<Arc<Progress>>,
+}
+
+// Sending never blocks, unless the bus is bounded and told to block when full
+#[derive(Clone)]
+pub(crate) struct EventSender {
+    tx: HubSender,
+    progress: Option<Arc<Progress>>,
+}
+
+#[derive(Clone)]
+enum HubSender {
+    Thread(Sender<Event>),
+    Task(UnboundedSender<Event>),
+    Bounded(BoundedSender<Event>),
+}
+
+// How far a hub on a thread of its own is with what was sent to it. Events a bounded bus had no room for, or
+// merged into others, count as done with.
+struct Progress {
+    tally: Mutex<Tally>,
+    handled: Condvar,
+    overflow: Option<Arc<OverflowCounts>>,
+}
+
+#[derive(Default)]
+struct Tally {
+    sent: usize,
+    handled: usize,
+}
+
+impl Progress {
+    fn new(overflow: Option<Arc<OverflowCounts>>) -> Arc<Self> {
+        Arc::new(Self {
+            tally: Mutex::new(Tally::default()),
+            handled: Condvar::new(),
+            overflow,
+        })
+    }
+
+    // Waits for the hub to be done with everything sent so far
+    fn catch_up(&self) {
+        let tally = self.tally.lock().unwrap();
+        let sent = tally.sent;
+        let behind = |tally: &mut Tally| {
+            let overflowed = self.overflow.as_ref().map_or(0, |counts| counts.dropped() + counts.coalesced());
+            tally.handled + overflowed < sent
+        };
+        drop(self.handled.wait_while(tally, behind).unwrap());
+    }
+}
+
+// What a participant sees of the events: whatever the hub's consumers publish as they handle them, the chat as
+// it comes out of the filters, and nothing a bounded bus had no room for.
+//
+// With the hub on a thread of its own, every look at the feed first waits for the hub to catch up with what was
+// sent: with the simulated clock only one participant runs at a time, so what everybody finds in the feed then
+// depends on nothing but virtual time. On an executor the hub is just another task, and needs no waiting for.
+pub(crate) struct Feed {
+    events: Subscriber<Event>,
+    progress: Option<Arc<Progress>>,
+}
+
+impl Feed {
+    pub(crate) fn try_recv(&self) -> Result<Event, TryRecvError> {
+        if let Some(progress) = &self.progress {
+            progress.catch_up();
+        }
+        self.events.try_recv()
+    }
+}
+
+impl EventSender {
+    pub(crate) fn send(&self, event: Event) {
+        match &self.tx {
+            HubSender::Thread(tx) => tx.send(event).unwrap(),
+            HubSender::Task(tx) => tx.unbounded_send(event).unwrap(),
+            HubSender::Bounded(tx) => tx.send(event),
+        }
+        // only once it is on its way, or dropped, so that the hub never looks further behind than it is
+        if let Some(progress) = &self.progress {
+            progress.tally.lock().unwrap().sent += 1;
+        }
+    }
+
+    // Follows what the hub's consumers publish to events, keeping up with the hub as explained above
+    pub(crate) fn follow(&self, events: Subscriber<Event>) -> Feed {
+        Feed {
+            events,
+            progress: self.progress.clone(),
+        }
+    }
+}
+
+pub(crate) fn event_bus() -> (EventSender, EventHub<Receiver<Event>>) {
+    let (tx, rx) = mpsc::channel();
+    let progress = Some(Progress::new(None));
+    (EventSender { tx: HubSender::Thread(tx), progress: progress.clone() }, EventHub::new(rx, progress))
+}
+
+pub(crate) fn async_event_bus() -> (EventSender, EventHub<UnboundedReceiver<Event>>) {
+    let (tx, rx) = async_mpsc::unbounded();
+    (EventSender { tx: HubSender::Task(tx), progress: None }, EventHub::new(rx, None))
+}
+
+// Up to capacity events waiting for the hub, chat messages that do not fit being dealt with as told by overflow
+// and counted in counts, for a hub on a thread of its own
+pub(crate) fn bounded_event_bus(capacity: usize, overflow: Overflow, counts: Arc<OverflowCounts>)
+                                -> (EventSender, EventHub<BoundedReceiver<Event>>) {
+    let progress = Some(Progress::new(Some(Arc::clone(&counts))));
+    let (tx, rx) = queues::bounded_channel(capacity, overflow, counts);
+    (EventSender { tx: HubSender::Bounded(tx), progress: progress.clone() }, EventHub::new(rx, progress))
+}
+
+// The same, for a hub running as a task
+pub(crate) fn bounded_async_event_bus(capacity: usize, overflow: Overflow, counts: Arc<OverflowCounts>)
+                                      -> (EventSender, EventHub<BoundedReceiver<Event>>) {
+    let (tx, rx) = queues::bounded_channel(capacity, overflow, counts);
+    (EventSender { tx: HubSender::Bounded(tx), progress: None }, EventHub::new(rx, None))
+}
+
+impl<R> EventHub<R> {
+    fn new(rx: R, progress: Option<Arc<Progress>>) -> Self {
+        Self {
+            rx,
+            consumers: vec![],
+            events: vec![],
+            progress,
+        }
+    }
+
+    pub(crate) fn add_consumer(&mut self, consumer: Box<dyn EventConsumer>) {
+        self.consumers.push(consumer);
+    }
+
+    fn deliver(&mut self, event: Event) {
+        for consumer in self.consumers.iter_mut() {
+            consumer.handle(&event);
+        }
+        self.events.push(event);
+        if let Some(progress) = &self.progress {
+            progress.tally.lock().unwrap().handled += 1;
+            progress.handled.notify_all();
+        }
+    }
+
+    fn finish(mut self) -> Vec<Event> {
+        for consumer in self.consumers.iter_mut() {
+            consumer.finish();
+        }
+        self.events
+    }
+}
+
+impl EventHub<Receiver<Event>> {
+    // Delivers events until every sender has been dropped, returning all of them in order
+    pub(crate) fn run(mut self) -> Vec<Event> {
+        while let Ok(event) = self.rx.recv() {
+            self.deliver(event);
+        }
+        self.finish()
+    }
+}
+
+impl EventHub<UnboundedReceiver<Event>> {
+    // The same, awaiting every event
+    pub(crate) async fn run_async(mut self) -> Vec<Event> {
+        while let Some(event) = self.rx.next().await {
+            self.deliver(event);
+        }
+        self.finish()
+    }
+}
+
+impl EventHub<BoundedReceiver<Event>> {
+    pub(crate) fn run(mut self) -> Vec<Event> {
+        while let Some(event) = self.rx.recv() {
+            self.deliver(event);
+        }
+        self.finish()
+    }
+
+    pub(crate) async fn run_async(mut self) -> Vec<Event> {
+        while let Some(event) = self.rx.recv_async().await {
+            self.deliver(event);
+        }
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::broadcast;
+    use crate::chat::{ChatMessage, ChatScreen};
+    use crate::clock::{Clock, SimulatedClock};
+    use crate::metrics::ChatLatencies;
+    use crate::moderation::ChatPipeline;
+    use crate::scenario::FilterConfig;
+
+    use super::*;
+
+    #[test]
+    fn students_never_see_what_moderation_or_a_full_bus_keeps_out_of_the_chat() {
+        let clock: Arc<dyn Clock> = Arc::new(SimulatedClock::starting_at_epoch());
+        let counts = Arc::new(OverflowCounts::default());
+        let (events_tx, mut hub) = bounded_event_bus(3, Overflow::DropNewest, Arc::clone(&counts));
+        let filters = FilterConfig {
+            duplicate_window_ms: Some(5000),
+            banned_words: vec![String::from("boring")],
+            ..FilterConfig::default()
+        };
+        let screen_feed = broadcast::channel();
+        let feed = events_tx.follow(screen_feed.subscribe(10));
+        hub.add_consumer(Box::new(ChatScreen::new(ChatPipeline::new(&filters), Arc::clone(&clock),
+                                                  Arc::new(ChatLatencies::default()), screen_feed)));
+
+        let chat = |username: &str, text: &str| Event::ChatMessage(ChatMessage::new(username, text, clock.now()));
+        events_tx.send(chat("Ana", "Hi!"));
+        // held back
+        events_tx.send(chat("Ana", "Hi!"));
+        // masked
+        events_tx.send(chat("Mat", "So boring"));
+        // no room left
+        events_tx.send(chat("Eve", "Bye!"));
+        drop(events_tx);
+        assert_eq!(hub.run().len(), 3);
+        assert_eq!(counts.dropped(), 1);
+
+        let mut seen = vec![];
+        loop {
+            match feed.try_recv() {
+                Ok(Event::ChatMessage(msg)) => seen.push(msg.text),
+                Ok(event) => panic!("nothing but chat was sent, got {:?}", event),
+                Err(TryRecvError::Closed) => break,
+                Err(error) => panic!("the feed has room for everything, got {:?}", error),
+            }
+        }
+        assert_eq!(seen, vec!["Hi!", "So ******"]);
+    }
+}
@@ -0,0 +1,175 @@
+use std::sync::Arc;
+use std::thread;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::broadcast::Publisher;
+use crate::bus::EventConsumer;
+use crate::clock::Clock;
+use crate::events::Event;
+use crate::metrics::ChatLatencies;
+use crate::moderation::{Action, ChatPipeline};
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct ChatMessage {
+    pub(crate) username: String,
+    pub(crate) text: String,
+    pub(crate) timestamp: DateTime<Utc>,
+    // the username of whoever wrote the message this one reacts to
+    pub(crate) in_reply_to: Option<String>,
+    // the title of the concept the message is about, if any, which a reaction shares with what it reacts to
+    pub(crate) concept: Option<String>,
+}
+
+impl ChatMessage {
+    pub(crate) fn new(username: &str, text: &str, timestamp: DateTime<Utc>) -> Self {
+        Self {
+            username: String::from(username),
+            text: String::from(text),
+            timestamp,
+            in_reply_to: None,
+            concept: None,
+        }
+    }
+
+    pub(crate) fn reply(username: &str, text: &str, timestamp: DateTime<Utc>, to: &ChatMessage) -> Self {
+        Self {
+            in_reply_to: Some(to.username.clone()),
+            concept: to.concept.clone(),
+            ..ChatMessage::new(username, text, timestamp)
+        }
+    }
+
+    pub(crate) fn about(self, concept: &str) -> Self {
+        Self {
+            concept: Some(String::from(concept)),
+            ..self
+        }
+    }
+}
+
+// Shows who comes and goes and every message sent to the chat, as it comes out of the filters, noting how long
+// every message took to get there (filtered out or not) by the session clock.
+//
+// Participants see the same screen: every event goes on to the feed, chat messages as shown, if at all.
+pub(crate) struct ChatScreen {
+    pipeline: ChatPipeline,
+    clock: Arc<dyn Clock>,
+    latencies: Arc<ChatLatencies>,
+    feed: Publisher<Event>,
+    received: Vec<ChatMessage>,
+}
+
+impl ChatScreen {
+    pub(crate) fn new(pipeline: ChatPipeline, clock: Arc<dyn Clock>, latencies: Arc<ChatLatencies>,
+                      feed: Publisher<Event>) -> Self {
+        Self {
+            pipeline,
+            clock,
+            latencies,
+            feed,
+            received: vec![],
+        }
+    }
+}
+
+impl EventConsumer for ChatScreen {
+    fn handle(&mut self, event: &Event) {
+        let tid = thread::current().id();
+        let shown = match event {
+            Event::Joined { name, .. } => {
+                println!("{:?} - ChatScreen: {} joined", tid, name);
+                Some(event.clone())
+            }
+            Event::Left { name, .. } => {
+                println!("{:?} - ChatScreen: {} left", tid, name);
+                Some(event.clone())
+            }
+            Event::ChatMessage(msg) | Event::Reaction(msg) => {
+                self.latencies.record(msg.timestamp, self.clock.now());
+                match self.pipeline.process(msg.clone()) {
+                    Some(shown) => {
+                        println!("{:?} - ChatScreen received: {:?}", tid, shown);
+                        self.received.push(shown.clone());
+                        match event {
+                            Event::Reaction(_) => Some(Event::Reaction(shown)),
+                            _ => Some(Event::ChatMessage(shown)),
+                        }
+                    }
+                    None => {
+                        println!("{:?} - ChatScreen held back a message from {}", tid, msg.username);
+                        None
+                    }
+                }
+            }
+            _ => Some(event.clone()),
+        };
+        if let Some(shown) = shown {
+            self.feed.publish(shown);
+        }
+    }
+
+    fn finish(&mut self) {
+        let tid = thread::current().id();
+        let received = &self.received;
+        println!("{:?}: Chat is closed, {} messages received", tid, received.len());
+        if let (Some(first), Some(last)) = (received.first(), received.last()) {
+            println!("{:?}: Chat was active for {}ms", tid, (last.timestamp - first.timestamp).num_milliseconds());
+        }
+        // per user, in order of their first message
+        let mut usernames: Vec<&str> = vec![];
+        for msg in received {
+            if !usernames.contains(&msg.username.as_str()) {
+                usernames.push(&msg.username);
+            }
+        }
+        for username in usernames {
+            let messages: Vec<&ChatMessage> = received.iter().filter(|msg| msg.username == username).collect();
+            println!("{:?}: {} sent {} messages, the last one was: {}",
+                     tid, username, messages.len(), messages[messages.len() - 1].text);
+        }
+        for action in self.pipeline.log() {
+            let (verb, reason) = match &action.action {
+                Action::Held(reason) => ("held back", reason),
+                Action::Edited(reason) => ("edited", reason),
+            };
+            println!("{:?}: Moderation: {} {} the message {} sent at {} ({})", tid, action.filter, verb,
+                     action.username, action.sent_at.format("%H:%M:%S%.3f"), reason);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::broadcast;
+    use crate::clock::SimulatedClock;
+    use crate::scenario::FilterConfig;
+
+    use super::*;
+
+    #[test]
+    fn latency_is_measured_by_the_session_clock_when_the_screen_gets_a_message() {
+        let clock: Arc<dyn Clock> = Arc::new(SimulatedClock::starting_at_epoch());
+        let sent_at = clock.now();
+        let latencies = Arc::new(ChatLatencies::default());
+        let filters = FilterConfig { duplicate_window_ms: Some(5000), ..FilterConfig::default() };
+        let mut screen = ChatScreen::new(ChatPipeline::new(&filters), Arc::clone(&clock), Arc::clone(&latencies),
+                                         broadcast::channel());
+
+        let actor = clock.register_actor();
+        clock.start(actor);
+        clock.sleep(actor, Duration::from_millis(250));
+        screen.handle(&Event::ChatMessage(ChatMessage::new("Ana", "Hi!", sent_at)));
+        clock.sleep(actor, Duration::from_millis(250));
+        // held back or not, the message made it to the screen
+        screen.handle(&Event::ChatMessage(ChatMessage::new("Ana", "Hi!", sent_at)));
+        clock.finish(actor);
+
+        let latency = latencies.measure();
+        assert_eq!((latency.messages, latency.mean_us, latency.max_us), (2, 375_000, 500_000));
+        assert_eq!(screen.received.len(), 1);
+    }
+}
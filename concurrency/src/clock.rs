@@ -0,0 +1,296 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, TimeZone, Utc};
+
+// Where participants get the time from, and how they let it pass.
+//
+// Participants are "actors": every one of them is registered (in a fixed order) before any is started,
+// calls start() first thing on its own thread, and finish() when it is done.
+pub(crate) trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+    fn sleep(&self, actor: ActorId, duration: Duration);
+    fn register_actor(&self) -> ActorId;
+    fn start(&self, actor: ActorId);
+    fn finish(&self, actor: ActorId);
+
+    // Blocks actor until ready() is true. Whoever changes what ready() depends on has to call unpark_all().
+    fn park(&self, actor: ActorId, ready: &dyn Fn() -> bool);
+    // Same, but gives up once timeout has passed
+    fn park_timeout(&self, actor: ActorId, ready: &dyn Fn() -> bool, timeout: Duration);
+    fn unpark_all(&self);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct ActorId(pub(crate) usize);
+
+// The wall clock: actors run freely and sleeping really takes that long
+#[derive(Default)]
+pub(crate) struct RealClock {
+    // bumped on every unpark_all(), so that parked actors never miss one
+    generation: Mutex<u64>,
+    unparked: Condvar,
+}
+
+impl Clock for RealClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn sleep(&self, _actor: ActorId, duration: Duration) {
+        thread::sleep(duration);
+    }
+
+    fn register_actor(&self) -> ActorId {
+        ActorId(0)
+    }
+
+    fn start(&self, _actor: ActorId) {}
+
+    fn finish(&self, _actor: ActorId) {}
+
+    fn park(&self, _actor: ActorId, ready: &dyn Fn() -> bool) {
+        loop {
+            let seen = *self.generation.lock().unwrap();
+            if ready() {
+                return;
+            }
+            // if anything changed since ready() was checked, the generation has moved on and there is no wait
+            let mut generation = self.generation.lock().unwrap();
+            while *generation == seen {
+                generation = self.unparked.wait(generation).unwrap();
+            }
+        }
+    }
+
+    fn park_timeout(&self, _actor: ActorId, ready: &dyn Fn() -> bool, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let seen = *self.generation.lock().unwrap();
+            if ready() {
+                return;
+            }
+            let mut generation = self.generation.lock().unwrap();
+            while *generation == seen {
+                let now = Instant::now();
+                if now >= deadline {
+                    return;
+                }
+                generation = self.unparked.wait_timeout(generation, deadline - now).unwrap().0;
+            }
+        }
+    }
+
+    fn unpark_all(&self) {
+        *self.generation.lock().unwrap() += 1;
+        self.unparked.notify_all();
+    }
+}
+
+// Virtual time for deterministic, instant simulations.
+//
+// Only one actor runs at a time. When it goes to sleep (or finishes), the actor with the earliest wake up
+// time runs next, and the clock jumps straight to that time. Actors waking up at the same time run in the
+// order they went to sleep, so every run of the same session produces exactly the same transcript.
+// Parked actors are out of the running until unparked, when they wake up right away.
+pub(crate) struct SimulatedClock {
+    start: DateTime<Utc>,
+    state: Mutex<SimulatedState>,
+    turn_changed: Condvar,
+}
+
+struct SimulatedState {
+    elapsed: Duration,
+    // wake up time and sequence number (which breaks ties fairly) of every sleeping actor
+    sleeping: BinaryHeap<Reverse<(Duration, u64, usize)>>,
+    next_sequence: u64,
+    next_actor: usize,
+    // the actor allowed to run, if any
+    running: Option<usize>,
+    parked: Vec<usize>,
+}
+
+impl SimulatedClock {
+    pub(crate) fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            start,
+            state: Mutex::new(SimulatedState {
+                elapsed: Duration::from_secs(0),
+                sleeping: BinaryHeap::new(),
+                next_sequence: 0,
+                next_actor: 0,
+                running: None,
+                parked: vec![],
+            }),
+            turn_changed: Condvar::new(),
+        }
+    }
+
+    // A fixed starting point, for reproducible timestamps
+    pub(crate) fn starting_at_epoch() -> Self {
+        SimulatedClock::new(Utc.ymd(2020, 1, 1).and_hms(9, 0, 0))
+    }
+
+    fn schedule_sleeper(state: &mut SimulatedState, actor: usize, wake_at: Duration) {
+        let sequence = state.next_sequence;
+        state.next_sequence += 1;
+        state.sleeping.push(Reverse((wake_at, sequence, actor)));
+    }
+
+    // Hands the turn to the next actor to wake up, moving the time forward to when it does
+    fn pass_turn(&self, state: &mut SimulatedState) {
+        state.running = None;
+        match state.sleeping.pop() {
+            Some(Reverse((wake_at, _, actor))) => {
+                state.elapsed = state.elapsed.max(wake_at);
+                state.running = Some(actor);
+            }
+            None if !state.parked.is_empty() => panic!("every actor is parked, none will ever be unparked"),
+            None => {}
+        }
+        self.turn_changed.notify_all();
+    }
+
+    fn wait_for_turn<'a>(&self, mut state: std::sync::MutexGuard<'a, SimulatedState>, actor: usize) {
+        while state.running != Some(actor) {
+            state = self.turn_changed.wait(state).unwrap();
+        }
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> DateTime<Utc> {
+        let elapsed = self.state.lock().unwrap().elapsed;
+        self.start + chrono::Duration::from_std(elapsed).unwrap()
+    }
+
+    fn sleep(&self, actor: ActorId, duration: Duration) {
+        let mut state = self.state.lock().unwrap();
+        let wake_at = state.elapsed + duration;
+        SimulatedClock::schedule_sleeper(&mut state, actor.0, wake_at);
+        self.pass_turn(&mut state);
+        self.wait_for_turn(state, actor.0);
+    }
+
+    // Registered actors start asleep, waking up right now, in the order they were registered
+    fn register_actor(&self) -> ActorId {
+        let mut state = self.state.lock().unwrap();
+        let actor = state.next_actor;
+        state.next_actor += 1;
+        let now = state.elapsed;
+        SimulatedClock::schedule_sleeper(&mut state, actor, now);
+        ActorId(actor)
+    }
+
+    fn start(&self, actor: ActorId) {
+        let mut state = self.state.lock().unwrap();
+        // the first actor to start gets things going
+        if state.running.is_none() {
+            self.pass_turn(&mut state);
+        }
+        self.wait_for_turn(state, actor.0);
+    }
+
+    fn finish(&self, _actor: ActorId) {
+        let mut state = self.state.lock().unwrap();
+        self.pass_turn(&mut state);
+    }
+
+    // Only the running actor can change anything, so nothing can change between checking and parking
+    fn park(&self, actor: ActorId, ready: &dyn Fn() -> bool) {
+        while !ready() {
+            let mut state = self.state.lock().unwrap();
+            state.parked.push(actor.0);
+            self.pass_turn(&mut state);
+            self.wait_for_turn(state, actor.0);
+        }
+    }
+
+    // Asleep and parked at the same time, until whichever comes first
+    fn park_timeout(&self, actor: ActorId, ready: &dyn Fn() -> bool, timeout: Duration) {
+        let deadline = self.state.lock().unwrap().elapsed + timeout;
+        while !ready() {
+            let mut state = self.state.lock().unwrap();
+            if state.elapsed >= deadline {
+                return;
+            }
+            state.parked.push(actor.0);
+            SimulatedClock::schedule_sleeper(&mut state, actor.0, deadline);
+            self.pass_turn(&mut state);
+            while state.running != Some(actor.0) {
+                state = self.turn_changed.wait(state).unwrap();
+            }
+            // woken up one way, so not the other
+            state.parked.retain(|&parked| parked != actor.0);
+            state.sleeping.retain(|Reverse((_, _, sleeper))| *sleeper != actor.0);
+        }
+    }
+
+    fn unpark_all(&self) {
+        let mut state = self.state.lock().unwrap();
+        let now = state.elapsed;
+        for actor in std::mem::take(&mut state.parked) {
+            SimulatedClock::schedule_sleeper(&mut state, actor, now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn actors_take_turns_in_virtual_time() {
+        let clock = Arc::new(SimulatedClock::starting_at_epoch());
+        let log = Arc::new(Mutex::new(vec![]));
+        let actors: Vec<(ActorId, u64)> = vec![(clock.register_actor(), 300), (clock.register_actor(), 200)];
+        let handles: Vec<_> = actors.into_iter().map(|(actor, step)| {
+            let clock = Arc::clone(&clock);
+            let log = Arc::clone(&log);
+            thread::spawn(move || {
+                clock.start(actor);
+                for _ in 0..3 {
+                    log.lock().unwrap().push((actor.0, clock.now().timestamp_millis() % 10_000));
+                    clock.sleep(actor, Duration::from_millis(step));
+                }
+                clock.finish(actor);
+            })
+        }).collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        let expected = vec![(0, 0), (1, 0), (1, 200), (0, 300), (1, 400), (0, 600)];
+        assert_eq!(*log.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn parked_actors_wake_up_when_unparked_or_at_the_latest_after_the_timeout() {
+        let clock = Arc::new(SimulatedClock::starting_at_epoch());
+        let flag = Arc::new(Mutex::new(false));
+        let parked = clock.register_actor();
+        let unparker = clock.register_actor();
+        let handle = {
+            let (clock, flag) = (Arc::clone(&clock), Arc::clone(&flag));
+            thread::spawn(move || {
+                clock.start(unparker);
+                clock.sleep(unparker, Duration::from_millis(200));
+                *flag.lock().unwrap() = true;
+                clock.unpark_all();
+                clock.finish(unparker);
+            })
+        };
+        let elapsed = || clock.now().timestamp_millis() % 10_000;
+        clock.start(parked);
+        clock.park_timeout(parked, &|| *flag.lock().unwrap(), Duration::from_millis(500));
+        assert_eq!(elapsed(), 200);
+        clock.park_timeout(parked, &|| false, Duration::from_millis(300));
+        assert_eq!(elapsed(), 500);
+        clock.finish(parked);
+        handle.join().unwrap();
+    }
+}
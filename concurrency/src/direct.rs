@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SendError, Sender};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use crate::clock::{ActorId, Clock};
+
+// A private message, only ever seen by whoever it is for: it never goes through the event bus, so it is not
+// in the transcript either
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct DirectMessage {
+    pub(crate) from: String,
+    pub(crate) to: String,
+    pub(crate) text: String,
+    pub(crate) sent_at: DateTime<Utc>,
+}
+
+// What lands in an inbox
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Delivery {
+    Message(DirectMessage),
+    // back to the sender, saying why
+    Undeliverable(DirectMessage, String),
+}
+
+// How many direct messages the router got through, and how many it could not
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct RoutingCounts {
+    pub(crate) delivered: usize,
+    pub(crate) undeliverable: usize,
+}
+
+// Gets direct messages to whoever they are for, by name.
+//
+// The router owns a directory of everybody's inboxes: participants only get the receiving end of their own, and
+// a sender to the router, in a Mailbox. It runs on a thread of its own until every mailbox is gone. Messages for
+// somebody unknown, or who has left already, go back to the sender instead.
+//
+// The router is an actor of the session clock like any participant, parked until a mailbox sends something or
+// goes away: with the simulated clock, a message is routed right after its sender's turn, and whether its
+// recipient has left by then depends on nothing but virtual time.
+pub(crate) struct Router {
+    clock: Arc<dyn Clock>,
+    inboxes: HashMap<String, Sender<Delivery>>,
+    messages_tx: Sender<DirectMessage>,
+    messages_rx: Receiver<DirectMessage>,
+    traffic: Arc<Traffic>,
+}
+
+// What the router keeps an eye on while parked
+#[derive(Default)]
+struct Traffic {
+    sent: AtomicUsize,
+    mailboxes: AtomicUsize,
+}
+
+// A participant's way to and from the router
+pub(crate) struct Mailbox {
+    name: String,
+    outbox: Sender<DirectMessage>,
+    inbox: Receiver<Delivery>,
+    clock: Arc<dyn Clock>,
+    traffic: Arc<Traffic>,
+}
+
+impl Router {
+    pub(crate) fn new(clock: Arc<dyn Clock>) -> Self {
+        let (messages_tx, messages_rx) = mpsc::channel();
+        Self {
+            clock,
+            inboxes: HashMap::new(),
+            messages_tx,
+            messages_rx,
+            traffic: Arc::new(Traffic::default()),
+        }
+    }
+
+    // Everybody has to be registered before the router starts
+    pub(crate) fn register(&mut self, name: &str) -> Mailbox {
+        let (inbox_tx, inbox_rx) = mpsc::channel();
+        self.inboxes.insert(String::from(name), inbox_tx);
+        self.traffic.mailboxes.fetch_add(1, Ordering::SeqCst);
+        Mailbox {
+            name: String::from(name),
+            outbox: Sender::clone(&self.messages_tx),
+            inbox: inbox_rx,
+            clock: Arc::clone(&self.clock),
+            traffic: Arc::clone(&self.traffic),
+        }
+    }
+
+    pub(crate) fn run(self, actor: ActorId) -> RoutingCounts {
+        let Router { clock, inboxes, messages_tx, messages_rx, traffic } = self;
+        drop(messages_tx);
+        clock.start(actor);
+        let mut counts = RoutingCounts::default();
+        let mut routed = 0;
+        loop {
+            // checked first: once every mailbox is gone, everything they sent is waiting already
+            let closed = traffic.mailboxes.load(Ordering::SeqCst) == 0;
+            for message in messages_rx.try_iter() {
+                routed += 1;
+                match route(&inboxes, message) {
+                    Ok(()) => counts.delivered += 1,
+                    Err((message, reason)) => {
+                        counts.undeliverable += 1;
+                        // the sender may have left too, in which case nobody will ever know
+                        if let Some(inbox) = inboxes.get(&message.from) {
+                            let _ = inbox.send(Delivery::Undeliverable(message, reason));
+                        }
+                    }
+                }
+            }
+            if closed {
+                break;
+            }
+            clock.park(actor, &|| {
+                traffic.sent.load(Ordering::SeqCst) > routed || traffic.mailboxes.load(Ordering::SeqCst) == 0
+            });
+        }
+        clock.finish(actor);
+        counts
+    }
+}
+
+fn route(inboxes: &HashMap<String, Sender<Delivery>>, message: DirectMessage) -> Result<(), (DirectMessage, String)> {
+    let inbox = match inboxes.get(&message.to) {
+        Some(inbox) => inbox,
+        None => {
+            let reason = format!("nobody called {} is here", message.to);
+            return Err((message, reason));
+        }
+    };
+    // an inbox whose owner has left is as good as none
+    match inbox.send(Delivery::Message(message)) {
+        Ok(()) => Ok(()),
+        Err(SendError(Delivery::Message(message))) => {
+            let reason = format!("{} has left", message.to);
+            Err((message, reason))
+        }
+        Err(SendError(Delivery::Undeliverable(..))) => unreachable!("only messages are sent to recipients"),
+    }
+}
+
+impl Mailbox {
+    pub(crate) fn send(&self, to: &str, text: &str, now: DateTime<Utc>) {
+        // the router only stops once every mailbox is gone, this one included
+        self.outbox.send(DirectMessage {
+            from: self.name.clone(),
+            to: String::from(to),
+            text: String::from(text),
+            sent_at: now,
+        }).unwrap();
+        self.traffic.sent.fetch_add(1, Ordering::SeqCst);
+        self.clock.unpark_all();
+    }
+
+    // Whatever arrived since last time, without waiting
+    pub(crate) fn receive(&self) -> Vec<Delivery> {
+        self.inbox.try_iter().collect()
+    }
+}
+
+// Leaving lets the router know right away, as the last one to go lets it finish
+impl Drop for Mailbox {
+    fn drop(&mut self) {
+        self.traffic.mailboxes.fetch_sub(1, Ordering::SeqCst);
+        self.clock.unpark_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use chrono::TimeZone;
+
+    use crate::clock::SimulatedClock;
+
+    use super::*;
+
+    fn at(ms: i64) -> DateTime<Utc> {
+        Utc.ymd(2020, 1, 1).and_hms(9, 0, 0) + chrono::Duration::milliseconds(ms)
+    }
+
+    fn message(from: &str, to: &str, text: &str) -> DirectMessage {
+        DirectMessage { from: String::from(from), to: String::from(to), text: String::from(text), sent_at: at(0) }
+    }
+
+    // The test itself is an actor, which lets the router take a turn by sleeping
+    fn start_router(names: &[&str]) -> (Arc<dyn Clock>, ActorId, Vec<Mailbox>, thread::JoinHandle<RoutingCounts>) {
+        let clock: Arc<dyn Clock> = Arc::new(SimulatedClock::starting_at_epoch());
+        let mut router = Router::new(Arc::clone(&clock));
+        let mailboxes = names.iter().map(|name| router.register(name)).collect();
+        let (actor, router_actor) = (clock.register_actor(), clock.register_actor());
+        let handle = thread::spawn(move || router.run(router_actor));
+        clock.start(actor);
+        (clock, actor, mailboxes, handle)
+    }
+
+    #[test]
+    fn messages_reach_whoever_they_are_for_or_go_back_to_the_sender() {
+        let (clock, actor, mut mailboxes, handle) = start_router(&["Ana", "Bob", "Cid"]);
+        drop(mailboxes.pop());
+        let (ana, bob) = (&mailboxes[0], &mailboxes[1]);
+        ana.send("Bob", "psst", at(0));
+        ana.send("Zoe", "hello?", at(0));
+        ana.send("Cid", "still there?", at(0));
+        bob.send("Ana", "what?", at(0));
+        clock.sleep(actor, Duration::from_millis(1));
+        drop(mailboxes);
+        clock.finish(actor);
+        let counts = handle.join().unwrap();
+        assert_eq!(counts, RoutingCounts { delivered: 2, undeliverable: 2 });
+    }
+
+    #[test]
+    fn only_the_recipient_gets_a_message_and_only_the_sender_hears_it_went_nowhere() {
+        let (clock, actor, mailboxes, handle) = start_router(&["Ana", "Bob", "Cid"]);
+        let (ana, bob, cid) = (&mailboxes[0], &mailboxes[1], &mailboxes[2]);
+        ana.send("Bob", "psst", at(0));
+        ana.send("Zoe", "hello?", at(0));
+        // routed as soon as the sender lets the router have a turn
+        assert_eq!(bob.receive(), vec![]);
+        clock.sleep(actor, Duration::from_millis(1));
+
+        assert_eq!(bob.receive(), vec![Delivery::Message(message("Ana", "Bob", "psst"))]);
+        let reason = String::from("nobody called Zoe is here");
+        assert_eq!(ana.receive(), vec![Delivery::Undeliverable(message("Ana", "Zoe", "hello?"), reason)]);
+        assert_eq!(cid.receive(), vec![]);
+        drop(mailboxes);
+        clock.finish(actor);
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::chat::ChatMessage;
+use crate::participants::Utterance;
+use crate::queues::Expendable;
+use crate::questions::{Answer, Question};
+
+// Everything that can happen during a session
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(crate) enum Event {
+    Joined { name: String, timestamp: DateTime<Utc> },
+    Left { name: String, timestamp: DateTime<Utc> },
+    ChatMessage(ChatMessage),
+    Spoke(Utterance),
+    ConceptStarted { title: String, timestamp: DateTime<Utc> },
+    ConceptEnded { title: String, timestamp: DateTime<Utc> },
+    Question(Question),
+    Answered(Answer),
+    // a chat message in reply to somebody else's
+    Reaction(ChatMessage),
+    // the class split between breakout rooms, with who is in which
+    RoomsOpened { rooms: Vec<Vec<String>>, timestamp: DateTime<Utc> },
+    // everything said in a breakout room, once everybody is back (rooms are numbered from 1)
+    RoomClosed { room: usize, members: Vec<String>, conversation: Vec<ChatMessage>, timestamp: DateTime<Utc> },
+}
+
+impl Event {
+    pub(crate) fn timestamp(&self) -> DateTime<Utc> {
+        match self {
+            Event::Joined { timestamp, .. } | Event::Left { timestamp, .. } => *timestamp,
+            Event::ConceptStarted { timestamp, .. } | Event::ConceptEnded { timestamp, .. } => *timestamp,
+            Event::ChatMessage(msg) | Event::Reaction(msg) => msg.timestamp,
+            Event::Spoke(utterance) => utterance.timestamp,
+            Event::Question(question) => question.asked_at,
+            Event::Answered(answer) => answer.answered_at,
+            Event::RoomsOpened { timestamp, .. } | Event::RoomClosed { timestamp, .. } => *timestamp,
+        }
+    }
+}
+
+// When the chat is full, chat messages can go, or be merged with the sender's previous one, nothing else can
+impl Expendable for Event {
+    fn is_expendable(&self) -> bool {
+        matches!(self, Event::ChatMessage(_) | Event::Reaction(_))
+    }
+
+    fn absorb(&mut self, later: Self) -> Result<(), Self> {
+        match (self, later) {
+            (Event::ChatMessage(msg), Event::ChatMessage(later)) | (Event::Reaction(msg), Event::Reaction(later))
+            if msg.username == later.username && msg.in_reply_to == later.in_reply_to
+                && msg.concept == later.concept => {
+                msg.text = format!("{} / {}", msg.text, later.text);
+                Ok(())
+            }
+            (_, later) => Err(later),
+        }
+    }
+}
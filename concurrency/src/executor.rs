@@ -0,0 +1,302 @@
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Weak};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::clock::{ActorId, Clock};
+
+type Task = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+// Runs tasks (futures) on one thread or more, letting time pass through the session clock.
+//
+// Tasks are polled in the order they are due: right away when woken, or once their sleep is over, ties going
+// to whoever was first to be woken or go to sleep. As far as the clock is concerned the executor is a single
+// actor: whenever no task is due, one of its threads waits through the clock until one is. On a single thread
+// tasks take turns exactly like actors on the simulated clock, so simulated sessions are reproducible. On more
+// threads, tasks due at the same time are polled in parallel.
+pub(crate) struct Executor {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    clock: Arc<dyn Clock>,
+    actor: ActorId,
+    state: Mutex<State>,
+    // for threads with nothing to do while others are polling
+    changed: Condvar,
+}
+
+struct State {
+    tasks: Vec<Slot>,
+    due: BinaryHeap<Reverse<Due>>,
+    next_sequence: u64,
+    // tasks not done yet
+    remaining: usize,
+    polling: usize,
+    // whether a thread is waiting through the clock
+    waiting: bool,
+}
+
+enum Slot {
+    Idle(Task),
+    // woken while being polled, to be polled again straight away
+    Polling { woken: bool },
+    Done,
+}
+
+// When a task is due
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Due {
+    at: DateTime<Utc>,
+    sequence: u64,
+    task: usize,
+}
+
+struct TaskWaker {
+    shared: Weak<Shared>,
+    task: usize,
+}
+
+thread_local! {
+    // the executor and task being polled on this thread, for sleeps to know who to wake up when
+    static CURRENT: RefCell<Option<(Arc<Shared>, usize)>> = const { RefCell::new(None) };
+}
+
+impl Executor {
+    // Registers the executor with the clock, see Clock
+    pub(crate) fn new(clock: Arc<dyn Clock>) -> Self {
+        let actor = clock.register_actor();
+        Self {
+            shared: Arc::new(Shared {
+                clock,
+                actor,
+                state: Mutex::new(State {
+                    tasks: vec![],
+                    due: BinaryHeap::new(),
+                    next_sequence: 0,
+                    remaining: 0,
+                    polling: 0,
+                    waiting: false,
+                }),
+                changed: Condvar::new(),
+            }),
+        }
+    }
+
+    pub(crate) fn spawn(&self, task: impl Future<Output = ()> + Send + 'static) {
+        let mut state = self.shared.state.lock().unwrap();
+        let id = state.tasks.len();
+        state.tasks.push(Slot::Idle(Box::pin(task)));
+        state.remaining += 1;
+        let now = self.shared.clock.now();
+        state.schedule(id, now);
+    }
+
+    // Polls tasks until every one of them is done, on this thread and as many more as it takes
+    pub(crate) fn run(self, threads: usize) {
+        assert!(threads > 0, "an executor needs at least one thread");
+        self.shared.clock.start(self.shared.actor);
+        let helpers: Vec<_> = (1..threads).map(|_| {
+            let shared = Arc::clone(&self.shared);
+            thread::spawn(move || shared.work())
+        }).collect();
+        self.shared.work();
+        for helper in helpers {
+            helper.join().unwrap();
+        }
+        self.shared.clock.finish(self.shared.actor);
+    }
+}
+
+impl State {
+    fn schedule(&mut self, task: usize, at: DateTime<Utc>) {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.due.push(Reverse(Due { at, sequence, task }));
+    }
+
+    fn next_due(&self) -> Option<DateTime<Utc>> {
+        self.due.peek().map(|Reverse(due)| due.at)
+    }
+}
+
+impl Shared {
+    fn work(self: &Arc<Self>) {
+        let mut state = self.state.lock().unwrap();
+        while state.remaining > 0 {
+            let now = self.clock.now();
+            if state.next_due().is_some_and(|at| at <= now) {
+                let Reverse(due) = state.due.pop().unwrap();
+                state = self.poll(state, due.task);
+            } else if state.polling > 0 || state.waiting {
+                // whoever is polling may wake somebody up, and whoever is waiting will let the others know
+                state = self.changed.wait(state).unwrap();
+            } else {
+                state.waiting = true;
+                let next_due = state.next_due();
+                drop(state);
+                let ready = || self.state.lock().unwrap().next_due().is_some_and(|at| at <= self.clock.now());
+                match next_due {
+                    Some(at) => self.clock.park_timeout(self.actor, &ready, (at - now).to_std().unwrap_or_default()),
+                    // only a task on another thread could ever wake anybody up
+                    None => self.clock.park(self.actor, &ready),
+                }
+                state = self.state.lock().unwrap();
+                state.waiting = false;
+                self.changed.notify_all();
+            }
+        }
+        self.changed.notify_all();
+    }
+
+    // Polls the task with the state unlocked in the meantime
+    fn poll<'a>(self: &'a Arc<Self>, mut state: MutexGuard<'a, State>, id: usize) -> MutexGuard<'a, State> {
+        // a task can be due more than once, say woken up before its sleep is over, or even while being polled
+        let mut task = match std::mem::replace(&mut state.tasks[id], Slot::Polling { woken: false }) {
+            Slot::Idle(task) => task,
+            Slot::Polling { .. } => {
+                state.tasks[id] = Slot::Polling { woken: true };
+                return state;
+            }
+            Slot::Done => {
+                state.tasks[id] = Slot::Done;
+                return state;
+            }
+        };
+        state.polling += 1;
+        drop(state);
+
+        let waker = Waker::from(Arc::new(TaskWaker { shared: Arc::downgrade(self), task: id }));
+        CURRENT.with(|current| *current.borrow_mut() = Some((Arc::clone(self), id)));
+        let poll = task.as_mut().poll(&mut Context::from_waker(&waker));
+        CURRENT.with(|current| *current.borrow_mut() = None);
+
+        let mut state = self.state.lock().unwrap();
+        state.polling -= 1;
+        let woken = matches!(state.tasks[id], Slot::Polling { woken: true });
+        if poll.is_ready() {
+            state.tasks[id] = Slot::Done;
+            state.remaining -= 1;
+        } else {
+            state.tasks[id] = Slot::Idle(task);
+            if woken {
+                let now = self.clock.now();
+                state.schedule(id, now);
+            }
+        }
+        self.changed.notify_all();
+        state
+    }
+
+    fn wake(&self, id: usize) {
+        let mut state = self.state.lock().unwrap();
+        match &mut state.tasks[id] {
+            Slot::Idle(_) => {
+                let now = self.clock.now();
+                state.schedule(id, now);
+            }
+            Slot::Polling { woken } => *woken = true,
+            Slot::Done => return,
+        }
+        let waiting = state.waiting;
+        drop(state);
+        self.changed.notify_all();
+        if waiting {
+            self.clock.unpark_all();
+        }
+    }
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        if let Some(shared) = self.shared.upgrade() {
+            shared.wake(self.task);
+        }
+    }
+}
+
+// Lets the time pass for the task being polled, which has to be on an executor
+pub(crate) fn sleep(duration: Duration) -> Sleep {
+    Sleep {
+        duration,
+        until: None,
+    }
+}
+
+pub(crate) struct Sleep {
+    duration: Duration,
+    until: Option<DateTime<Utc>>,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    // Always gives others a turn, even when sleeping for no time at all
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context) -> Poll<()> {
+        let duration = self.duration;
+        CURRENT.with(|current| {
+            let current = current.borrow();
+            let (shared, id) = current.as_ref().expect("sleeping outside of an executor");
+            let now = shared.clock.now();
+            match self.until {
+                Some(until) if now >= until => Poll::Ready(()),
+                _ => {
+                    let until = *self.until.get_or_insert_with(|| now + chrono::Duration::from_std(duration).unwrap());
+                    shared.state.lock().unwrap().schedule(*id, until);
+                    Poll::Pending
+                }
+            }
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use crate::clock::{RealClock, SimulatedClock};
+
+    use super::*;
+
+    // (task, milliseconds since the start) every time a task wakes up
+    fn run_sleepers(clock: Arc<dyn Clock>, threads: usize) -> Vec<(usize, i64)> {
+        let start = clock.now();
+        let executor = Executor::new(Arc::clone(&clock));
+        let (log_tx, log_rx) = mpsc::channel();
+        for (task, step) in [(0, 30), (1, 20)].iter().copied() {
+            let (clock, log_tx) = (Arc::clone(&clock), log_tx.clone());
+            executor.spawn(async move {
+                for _ in 0..3 {
+                    log_tx.send((task, (clock.now() - start).num_milliseconds())).unwrap();
+                    sleep(Duration::from_millis(step)).await;
+                }
+            });
+        }
+        drop(log_tx);
+        executor.run(threads);
+        log_rx.iter().collect()
+    }
+
+    #[test]
+    fn tasks_take_turns_in_virtual_time() {
+        let expected = vec![(0, 0), (1, 0), (1, 20), (0, 30), (1, 40), (0, 60)];
+        assert_eq!(run_sleepers(Arc::new(SimulatedClock::starting_at_epoch()), 1), expected);
+    }
+
+    #[test]
+    fn sleeping_takes_real_time_on_every_thread() {
+        let mut log = run_sleepers(Arc::new(RealClock::default()), 2);
+        assert_eq!(log.len(), 6);
+        log.sort_unstable();
+        // tasks wake up late, never early
+        assert!(log[2].1 >= 60 && log[5].1 >= 40);
+    }
+}
@@ -0,0 +1,277 @@
+use std::fmt::Write;
+
+use crate::chat::ChatMessage;
+use crate::events::Event;
+use crate::questions::Answer;
+
+// One JSON object per event, one event per line
+pub(crate) fn json_lines(events: &[Event]) -> String {
+    let mut lines = String::new();
+    for event in events {
+        lines.push_str(&serde_json::to_string(event).expect("events can always be serialized"));
+        lines.push('\n');
+    }
+    lines
+}
+
+// What was covered during a concept, from the moment it started until the next one did
+#[derive(Default)]
+struct ConceptNotes<'a> {
+    title: &'a str,
+    chat: Vec<&'a ChatMessage>,
+    tricky_for: Vec<&'a str>,
+    answers: Vec<&'a Answer>,
+}
+
+// Human readable meeting minutes, in Markdown
+pub(crate) fn minutes(events: &[Event]) -> String {
+    let mut attendees: Vec<&str> = vec![];
+    // talkers and how many times they spoke, in order of their first time
+    let mut speakers: Vec<(&str, usize)> = vec![];
+    // chat before the first concept starts is kept apart
+    let mut before: ConceptNotes = ConceptNotes::default();
+    // every concept presented, in order, so that questions can be filed even if asked before the concept started
+    let mut concepts: Vec<ConceptNotes> = events.iter().filter_map(|event| match event {
+        Event::ConceptStarted { title, .. } => Some(ConceptNotes { title, ..ConceptNotes::default() }),
+        _ => None,
+    }).collect();
+    // breakout rooms, in the order they were closed, with who was in them and what they said
+    let mut rooms: Vec<(usize, &[String], Vec<&ChatMessage>)> = vec![];
+    let mut current: Option<usize> = None;
+    for event in events {
+        match event {
+            Event::Joined { name, .. } => attendees.push(name),
+            Event::ConceptStarted { .. } => current = Some(current.map_or(0, |idx| idx + 1)),
+            // chat goes with the concept it is about, which may well have ended already, or else with the one
+            // being presented when it was sent
+            Event::ChatMessage(msg) | Event::Reaction(msg) => {
+                let started = current.map_or(0, |idx| idx + 1);
+                let about = msg.concept.as_ref()
+                    .and_then(|title| concepts[..started].iter().rposition(|notes| notes.title == title));
+                match about.or(current) {
+                    Some(idx) => concepts[idx].chat.push(msg),
+                    None => before.chat.push(msg),
+                }
+            }
+            Event::Spoke(utterance) => match speakers.iter_mut().find(|(name, _)| *name == utterance.name) {
+                Some((_, times)) => *times += 1,
+                None => speakers.push((&utterance.name, 1)),
+            },
+            // questions and answers belong to the concept they are about, whenever they happen
+            Event::Question(question) => {
+                if let Some(notes) = concepts.iter_mut().find(|notes| notes.title == question.concept) {
+                    notes.tricky_for.push(&question.student);
+                }
+            }
+            Event::Answered(answer) => {
+                if let Some(notes) = concepts.iter_mut().find(|notes| notes.title == answer.question.concept) {
+                    notes.answers.push(answer);
+                }
+            }
+            Event::RoomClosed { room, members, conversation, .. } => {
+                rooms.push((*room, members, conversation.iter().collect()));
+            }
+            Event::Left { .. } | Event::ConceptEnded { .. } | Event::RoomsOpened { .. } => {}
+        }
+    }
+
+    let mut md = String::new();
+    // writing to a String never fails
+    writeln!(md, "# Training session minutes").unwrap();
+    if let (Some(first), Some(last)) = (events.first(), events.last()) {
+        writeln!(md).unwrap();
+        writeln!(md, "{}, from {} to {} UTC", first.timestamp().format("%Y-%m-%d"),
+                 first.timestamp().format("%H:%M:%S"), last.timestamp().format("%H:%M:%S")).unwrap();
+    }
+    writeln!(md).unwrap();
+    writeln!(md, "Attendees: {}", attendees.join(", ")).unwrap();
+
+    if !before.chat.is_empty() {
+        writeln!(md).unwrap();
+        writeln!(md, "## Before the first concept").unwrap();
+        write_chat(&mut md, &before.chat);
+    }
+    for (idx, notes) in concepts.iter().enumerate() {
+        writeln!(md).unwrap();
+        writeln!(md, "## {}. {}", idx + 1, notes.title).unwrap();
+        if notes.tricky_for.is_empty() {
+            writeln!(md).unwrap();
+            writeln!(md, "Nobody found it tricky.").unwrap();
+        } else {
+            writeln!(md).unwrap();
+            writeln!(md, "Found it tricky: {}", notes.tricky_for.join(", ")).unwrap();
+        }
+        if !notes.answers.is_empty() {
+            writeln!(md).unwrap();
+            writeln!(md, "Questions:").unwrap();
+            writeln!(md).unwrap();
+            for answer in &notes.answers {
+                writeln!(md, "- {} answered {} after {}ms", answer.presenter, answer.question.student,
+                         answer.waited().num_milliseconds()).unwrap();
+            }
+        }
+        write_chat(&mut md, &notes.chat);
+    }
+
+    if !rooms.is_empty() {
+        writeln!(md).unwrap();
+        writeln!(md, "## Breakout rooms").unwrap();
+        for (room, members, conversation) in rooms {
+            writeln!(md).unwrap();
+            writeln!(md, "### Room {}: {}", room, members.join(", ")).unwrap();
+            if conversation.is_empty() {
+                writeln!(md).unwrap();
+                writeln!(md, "Nobody said anything.").unwrap();
+            }
+            write_chat(&mut md, &conversation);
+        }
+    }
+
+    writeln!(md).unwrap();
+    writeln!(md, "## Who spoke").unwrap();
+    writeln!(md).unwrap();
+    for (name, times) in speakers {
+        writeln!(md, "- {}: {} {}", name, times, if times == 1 { "time" } else { "times" }).unwrap();
+    }
+    md
+}
+
+fn write_chat(md: &mut String, chat: &[&ChatMessage]) {
+    if chat.is_empty() {
+        return;
+    }
+    writeln!(md).unwrap();
+    writeln!(md, "Chat:").unwrap();
+    writeln!(md).unwrap();
+    for msg in chat {
+        writeln!(md, "- {} **{}**: {}", msg.timestamp.format("%H:%M:%S"), msg.username, msg.text).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    use crate::clock::SimulatedClock;
+    use crate::participants::Utterance;
+    use crate::questions::Question;
+    use crate::scenario::Scenario;
+    use crate::session::{run_session, Execution};
+
+    use super::*;
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        Utc.ymd(2020, 1, 1).and_hms(9, 0, 0) + Duration::seconds(seconds)
+    }
+
+    fn events() -> Vec<Event> {
+        let question = Question::new("Ana", "Traits", at(3));
+        vec![
+            Event::Joined { name: String::from("Mat"), timestamp: at(0) },
+            Event::Joined { name: String::from("Ana"), timestamp: at(0) },
+            Event::ChatMessage(ChatMessage::new("Ana", "Hi!", at(0))),
+            Event::ConceptStarted { title: String::from("Traits"), timestamp: at(1) },
+            Event::Spoke(Utterance { name: String::from("Mat"), text: String::from("Traits..."), timestamp: at(1) }),
+            Event::Spoke(Utterance { name: String::from("Ana"), text: String::from("Tricky!"), timestamp: at(3) }),
+            Event::Question(question.clone()),
+            Event::ConceptEnded { title: String::from("Traits"), timestamp: at(4) },
+            Event::Answered(Answer { question, presenter: String::from("Mat"), answered_at: at(5) }),
+            Event::Left { name: String::from("Ana"), timestamp: at(6) },
+            Event::Left { name: String::from("Mat"), timestamp: at(7) },
+        ]
+    }
+
+    #[test]
+    fn one_json_object_per_event_with_rfc_3339_timestamps() {
+        let lines = json_lines(&events());
+        assert_eq!(lines.lines().count(), 11);
+        assert_eq!(lines.lines().nth(2).unwrap(),
+                   concat!(r#"{"event":"chat_message","username":"Ana","text":"Hi!","#,
+                           r#""timestamp":"2020-01-01T09:00:00Z","in_reply_to":null,"concept":null}"#));
+        assert_eq!(lines.lines().nth(8).unwrap(),
+                   concat!(r#"{"event":"answered","question":{"student":"Ana","concept":"Traits","#,
+                           r#""asked_at":"2020-01-01T09:00:03Z"},"presenter":"Mat","#,
+                           r#""answered_at":"2020-01-01T09:00:05Z"}"#));
+    }
+
+    #[test]
+    fn minutes_group_everything_by_concept() {
+        let expected = "\
+# Training session minutes
+
+2020-01-01, from 09:00:00 to 09:00:07 UTC
+
+Attendees: Mat, Ana
+
+## Before the first concept
+
+Chat:
+
+- 09:00:00 **Ana**: Hi!
+
+## 1. Traits
+
+Found it tricky: Ana
+
+Questions:
+
+- Mat answered Ana after 2000ms
+
+## Who spoke
+
+- Mat: 1 time
+- Ana: 1 time
+";
+        assert_eq!(minutes(&events()), expected);
+    }
+
+    #[test]
+    fn minutes_have_every_breakout_room_after_the_concepts() {
+        let members = |names: &[&str]| names.iter().map(|name| String::from(*name)).collect::<Vec<_>>();
+        let mut events = events();
+        events.insert(8, Event::RoomsOpened { rooms: vec![members(&["Ana"]), members(&["Mat"])], timestamp: at(4) });
+        events.insert(9, Event::RoomClosed { room: 1, members: members(&["Ana"]),
+                                             conversation: vec![ChatMessage::new("Ana", "So?", at(4))],
+                                             timestamp: at(5) });
+        events.insert(10, Event::RoomClosed { room: 2, members: members(&["Mat"]), conversation: vec![],
+                                              timestamp: at(5) });
+        let minutes = minutes(&events);
+        let rooms = "\
+## Breakout rooms
+
+### Room 1: Ana
+
+Chat:
+
+- 09:00:04 **Ana**: So?
+
+### Room 2: Mat
+
+Nobody said anything.
+
+## Who spoke
+";
+        assert!(minutes.contains(rooms), "{}", minutes);
+    }
+
+    #[test]
+    fn minutes_of_a_session_have_the_chat_about_every_concept_under_it() {
+        let clock = Arc::new(SimulatedClock::starting_at_epoch());
+        let record = run_session(Scenario::default(), clock, Execution::ThreadPerStudent);
+        let minutes = minutes(&record.transcript.events);
+
+        // students take a while to take a concept in, so they often say what they think of it once the next one
+        // has started
+        let mut cool = 0;
+        for section in minutes.split("\n## ").filter(|section| section.starts_with(char::is_numeric)) {
+            let title = section.lines().next().unwrap().split_once(". ").unwrap().1;
+            for line in section.lines().filter(|line| line.ends_with(" is cool!")) {
+                assert!(line.ends_with(&format!("{} is cool!", title)), "{} under {}", line, title);
+                cool += 1;
+            }
+        }
+        assert!(cool > 0, "{}", minutes);
+    }
+}
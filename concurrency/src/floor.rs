@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+// Decides who may speak: only one participant holds the floor at a time.
+//
+// Anybody who wants to speak raises their hand and waits to be given the floor. Hands are taken first come,
+// first served, except for the presenter's, which goes to the front of the queue. The presenter still waits
+// for whoever is speaking to finish, so nobody is ever interrupted, unless they have been speaking for longer
+// than the maximum speaking time while others were waiting: then they are cut off.
+//
+// Nothing here blocks. Waiting is up to the participants, who check for their turn as they see fit.
+pub(crate) struct Floor {
+    presenter: String,
+    max_speaking_time: chrono::Duration,
+    state: Mutex<FloorState>,
+}
+
+// Who held the floor, from when until when
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct FloorTurn {
+    pub(crate) name: String,
+    pub(crate) from: DateTime<Utc>,
+    pub(crate) until: DateTime<Utc>,
+    pub(crate) cut_off: bool,
+}
+
+struct FloorState {
+    holder: Option<(String, DateTime<Utc>)>,
+    hands: VecDeque<String>,
+    log: Vec<FloorTurn>,
+}
+
+impl Floor {
+    pub(crate) fn new(presenter: &str, max_speaking_time: Duration) -> Self {
+        Self {
+            presenter: String::from(presenter),
+            max_speaking_time: chrono::Duration::from_std(max_speaking_time).unwrap(),
+            state: Mutex::new(FloorState {
+                holder: None,
+                hands: VecDeque::new(),
+                log: vec![],
+            }),
+        }
+    }
+
+    pub(crate) fn raise_hand(&self, name: &str) {
+        let mut state = self.state.lock().unwrap();
+        let holding = state.holder.as_ref().is_some_and(|(holder, _)| holder == name);
+        if holding || state.hands.iter().any(|hand| hand == name) {
+            return;
+        }
+        if name == self.presenter {
+            state.hands.push_front(String::from(name));
+        } else {
+            state.hands.push_back(String::from(name));
+        }
+    }
+
+    // Changed their mind, or gave up waiting
+    pub(crate) fn lower_hand(&self, name: &str) {
+        self.state.lock().unwrap().hands.retain(|hand| hand != name);
+    }
+
+    // Whether name holds the floor now, taking it if it is free and it is their turn
+    pub(crate) fn try_take(&self, name: &str, now: DateTime<Utc>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if let Some((holder, since)) = state.holder.clone() {
+            if holder == name {
+                return true;
+            }
+            if now - since <= self.max_speaking_time || state.hands.is_empty() {
+                return false;
+            }
+            state.log.push(FloorTurn { name: holder, from: since, until: now, cut_off: true });
+            state.holder = None;
+        }
+        if state.hands.front().is_some_and(|hand| hand == name) {
+            state.hands.pop_front();
+            state.holder = Some((String::from(name), now));
+            true
+        } else {
+            false
+        }
+    }
+
+    // The floor goes straight to the next raised hand, if any. Returns false if name had been cut off.
+    pub(crate) fn release(&self, name: &str, now: DateTime<Utc>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.holder.take() {
+            Some((holder, since)) if holder == name => {
+                state.log.push(FloorTurn { name: holder, from: since, until: now, cut_off: false });
+                state.holder = state.hands.pop_front().map(|next| (next, now));
+                true
+            }
+            other => {
+                state.holder = other;
+                false
+            }
+        }
+    }
+
+    // Every turn so far, in the order they ended
+    pub(crate) fn log(&self) -> Vec<FloorTurn> {
+        self.state.lock().unwrap().log.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(ms: i64) -> DateTime<Utc> {
+        Utc.ymd(2020, 1, 1).and_hms(9, 0, 0) + chrono::Duration::milliseconds(ms)
+    }
+
+    #[test]
+    fn students_take_turns_after_the_presenter() {
+        let floor = Floor::new("Mat", Duration::from_secs(5));
+        floor.raise_hand("Ana");
+        assert!(floor.try_take("Ana", at(0)));
+        floor.raise_hand("Bruno");
+        floor.raise_hand("Carla");
+        floor.raise_hand("Mat");
+        // nobody is interrupted, not even by the presenter
+        assert!(!floor.try_take("Mat", at(100)));
+        assert!(floor.release("Ana", at(200)));
+        // but the presenter goes first, and then everybody else in order
+        assert!(!floor.try_take("Bruno", at(200)));
+        assert!(floor.try_take("Mat", at(200)));
+        assert!(floor.release("Mat", at(300)));
+        assert!(!floor.try_take("Carla", at(300)));
+        assert!(floor.try_take("Bruno", at(300)));
+        floor.lower_hand("Carla");
+        assert!(floor.release("Bruno", at(400)));
+        assert!(!floor.try_take("Carla", at(400)));
+        let turns: Vec<_> = floor.log().into_iter().map(|turn| turn.name).collect();
+        assert_eq!(turns, vec!["Ana", "Mat", "Bruno"]);
+    }
+
+    #[test]
+    fn speaking_for_too_long_gets_you_cut_off() {
+        let floor = Floor::new("Mat", Duration::from_secs(1));
+        floor.raise_hand("Ana");
+        assert!(floor.try_take("Ana", at(0)));
+        floor.raise_hand("Bruno");
+        assert!(!floor.try_take("Bruno", at(1000)));
+        assert!(floor.try_take("Bruno", at(1001)));
+        assert!(!floor.release("Ana", at(1500)));
+        let cut_off = FloorTurn { name: String::from("Ana"), from: at(0), until: at(1001), cut_off: true };
+        assert_eq!(floor.log(), vec![cut_off]);
+    }
+}
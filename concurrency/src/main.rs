@@ -1,140 +1,242 @@
-use std::sync::mpsc;
-use std::sync::mpsc::{Receiver, Sender};
-use std::sync::Mutex;
+use std::env;
+use std::fs;
+use std::process::exit;
+use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
-
-use chrono::{DateTime, Utc};
-use lazy_static::lazy_static;
-
-const NUMBER_OF_STUDENTS: u8 = 3;
-const NUMBER_OF_CONCEPTS: u8 = 6;
-
-#[derive(Debug)]
-struct ChatMessage {
-    username: String,
-    text: String,
-    timestamp: DateTime<Utc>,
+use std::time::Instant;
+
+use crate::bench::BenchConfig;
+use crate::clock::{Clock, RealClock, SimulatedClock};
+use crate::queues::Overflow;
+use crate::scenario::Scenario;
+use crate::session::Execution;
+
+mod bench;
+mod board;
+mod breakout;
+mod broadcast;
+mod bus;
+mod chat;
+mod clock;
+mod direct;
+mod events;
+mod executor;
+mod export;
+mod floor;
+mod metrics;
+mod moderation;
+mod participants;
+mod pool;
+mod queues;
+mod questions;
+mod scenario;
+mod session;
+mod tasks;
+
+const SCENARIO_FLAG: &str = "--scenario";
+const SIMULATED_FLAG: &str = "--simulated";
+const TRANSCRIPT_FLAG: &str = "--transcript";
+const MINUTES_FLAG: &str = "--minutes";
+const METRICS_FLAG: &str = "--metrics";
+const POOL_FLAG: &str = "--pool";
+const CLASS_SIZE_FLAG: &str = "--class-size";
+const COMPARE_FLAG: &str = "--compare";
+const ASYNC_FLAG: &str = "--async";
+const BENCH_COMMAND: &str = "bench";
+const STUDENTS_FLAG: &str = "--students";
+const MESSAGES_FLAG: &str = "--messages";
+const CSV_FLAG: &str = "--csv";
+const CHAT_CAPACITY_FLAG: &str = "--chat-capacity";
+const OVERFLOW_FLAG: &str = "--overflow";
+
+fn usage() -> ! {
+    eprintln!("Usage: concurrency [{} <file>] [{}] [{} <file>] [{} <file>] [{} <file>] \
+               [{} <workers> | {} <threads>] [{} <students>] [{}] [{} <messages>] \
+               [{} block|drop-newest|drop-oldest|coalesce]",
+              SCENARIO_FLAG, SIMULATED_FLAG, TRANSCRIPT_FLAG, MINUTES_FLAG, METRICS_FLAG, POOL_FLAG, ASYNC_FLAG,
+              CLASS_SIZE_FLAG, COMPARE_FLAG, CHAT_CAPACITY_FLAG, OVERFLOW_FLAG);
+    eprintln!("       concurrency {} [{} <students>,...] [{} <per student>] [{} <workers>] [{} <file>]",
+              BENCH_COMMAND, STUDENTS_FLAG, MESSAGES_FLAG, POOL_FLAG, CSV_FLAG);
+    exit(2);
 }
 
-impl ChatMessage {
-    fn new(username: &str, text: &str) -> Self {
-        Self {
-            username: String::from(username),
-            text: String::from(text),
-            timestamp: Utc::now(),
-        }
+// The value given right after flag, if the flag is there at all
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    let idx = args.iter().position(|arg| arg == flag)?;
+    match args.get(idx + 1) {
+        Some(value) => Some(value),
+        None => usage(),
     }
 }
 
-struct ChatScreen {
-    rx: Receiver<ChatMessage>,
-}
-
-impl ChatScreen {
-    fn handle_messages(&self) {
-        let tid = thread::current().id();
-        println!("{:?}: Chat is ready", tid);
-        // receiver will block until messages arrive
-        for msg in &self.rx {
-            println!("{:?} - ChatScreen received: {:?}", tid, msg);
+// A positive number given right after flag, if the flag is there at all
+fn flag_count(args: &[String], flag: &str) -> Option<usize> {
+    let value = flag_value(args, flag)?;
+    match value.parse() {
+        Ok(count) if count > 0 => Some(count),
+        _ => {
+            eprintln!("{} needs a positive number, not {}", flag, value);
+            usage();
         }
     }
 }
 
-lazy_static! {
-    static ref SPEAK_MUTEX: Mutex<i32> = Mutex::new(0i32);
-}
-
-fn say_stuff(stuff: &str, name: &str) {
-    let _lock = SPEAK_MUTEX.lock().unwrap();
-    println!("{:?} - {} says: {}", thread::current().id(), name, stuff);
+fn cpus() -> usize {
+    thread::available_parallelism().map_or(4, |cpus| cpus.get())
 }
 
-struct Presenter {
-    name: String,
+// with --simulated the session runs in virtual time, so it is over in an instant
+fn new_clock(args: &[String]) -> Arc<dyn Clock> {
+    if args.iter().any(|arg| arg == SIMULATED_FLAG) {
+        Arc::new(SimulatedClock::starting_at_epoch())
+    } else {
+        Arc::new(RealClock::default())
+    }
 }
 
-impl Presenter {
-    fn new(name: &str) -> Self {
-        Self {
-            name: String::from(name),
-        }
+// Runs the same scenario with a thread per student, on a pool and async on one thread and more (as many as there
+// are CPUs, unless given with --pool or --async), and sums up how every run went
+fn compare(args: &[String], scenario: Scenario) {
+    let workers = flag_count(args, POOL_FLAG).unwrap_or_else(cpus);
+    let threads = flag_count(args, ASYNC_FLAG).unwrap_or_else(cpus);
+    let students = scenario.students.len();
+    let rooms = scenario.breakout_rooms();
+    let executions = [Execution::ThreadPerStudent, Execution::Pool { workers }, Execution::Async { threads: 1 },
+                      Execution::Async { threads }];
+    let mut results = vec![];
+    for execution in executions.iter() {
+        println!();
+        println!("{:?}: Start of training session, with {}", thread::current().id(), execution);
+        let started = Instant::now();
+        let record = session::run_session(scenario.clone(), new_clock(args), *execution);
+        results.push((*execution, started.elapsed(), record.transcript));
     }
 
-    fn present(&self) {
-        for i in 1..NUMBER_OF_CONCEPTS + 1 {
-            say_stuff(format!("Let me tell you about thing #{} that is amazing about Rust...", i).as_str(),
-                      self.name.as_str());
-            thread::sleep(Duration::from_millis(1500));
-        }
+    println!();
+    println!("{:?}: {} students:", thread::current().id(), students);
+    println!("{:<24} {:>8} {:>10} {:>12} {:>8} {:>8} {:>8}", "run on", "threads", "wall (ms)", "session (ms)",
+             "events", "spoken", "chat");
+    for (execution, wall_time, transcript) in &results {
+        println!("{:<24} {:>8} {:>10} {:>12} {:>8} {:>8} {:>8}", execution.to_string(),
+                 execution.threads(students, rooms), wall_time.as_millis(), transcript.duration().num_milliseconds(),
+                 transcript.events.len(), transcript.spoken().len(), transcript.chat().len());
     }
 }
 
-struct Student {
-    name: String,
-    tx: Sender<ChatMessage>,
-}
+// Benchmarks whole sessions, the chat and the floor with classes of 10 to 100,000 students, unless given with
+// --students
+fn bench(args: &[String]) {
+    let class_sizes = match flag_value(args, STUDENTS_FLAG) {
+        Some(sizes) => sizes.split(',').map(|size| match size.parse() {
+            Ok(size) if size > 0 => size,
+            _ => {
+                eprintln!("{} needs positive numbers, not {}", STUDENTS_FLAG, size);
+                usage();
+            }
+        }).collect(),
+        None => vec![10, 100, 1_000, 10_000, 100_000],
+    };
+    let config = BenchConfig {
+        class_sizes,
+        messages: flag_count(args, MESSAGES_FLAG).unwrap_or(10),
+        workers: flag_count(args, POOL_FLAG).unwrap_or_else(cpus),
+    };
+
+    println!("{:?}: Benchmarking with {} messages per student on {} worker{}", thread::current().id(), config.messages,
+             config.workers, if config.workers == 1 { "" } else { "s" });
+    let results = bench::run(&config);
+    println!();
+    print!("{}", bench::report(&results));
 
-impl Student {
-    fn new(name: &str, tx: Sender<ChatMessage>) -> Self {
-        Self {
-            name: String::from(name),
-            tx,
+    if let Some(path) = flag_value(args, CSV_FLAG) {
+        if let Err(e) = fs::write(path, bench::csv(&results)) {
+            eprintln!("could not write {}: {}", path, e);
+            exit(1);
         }
+        println!();
+        println!("{:?}: Wrote {}", thread::current().id(), path);
     }
+}
 
-    fn active_listen(&self) {
-        for i in 1..NUMBER_OF_CONCEPTS + 1 {
-            if i % 3 == 0 {
-                say_stuff(format!("Amazing thing #{} sounds tricky :-(", i).as_str(), self.name.as_str());
-            } else {
-                let message = ChatMessage::new(&self.name,
-                                               format!("Amazing thing #{} is cool!", i).as_str());
-                self.tx.send(message).unwrap();
-            }
-            thread::sleep(Duration::from_millis(1700));
+// The scenario comes from the file given with --scenario, or else the built in default one
+fn load_scenario(args: &[String]) -> Scenario {
+    let path = match flag_value(args, SCENARIO_FLAG) {
+        Some(path) => path,
+        None => return Scenario::default(),
+    };
+    match Scenario::load(path) {
+        Ok(scenario) => scenario,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
         }
     }
 }
 
 fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some(BENCH_COMMAND) {
+        bench(&args);
+        return;
+    }
+    let mut scenario = load_scenario(&args);
+    if let Some(students) = flag_count(&args, CLASS_SIZE_FLAG) {
+        scenario = scenario.with_class_size(students);
+    }
+    if let Some(capacity) = flag_count(&args, CHAT_CAPACITY_FLAG) {
+        scenario.chat.capacity = Some(capacity);
+    }
+    if let Some(policy) = flag_value(&args, OVERFLOW_FLAG) {
+        scenario.chat.overflow = policy.parse().unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            usage();
+        });
+    }
+    // a task waiting for room in the chat would hold up its thread, and possibly the event bus with it
+    let runs_async = args.iter().any(|arg| arg == COMPARE_FLAG) || flag_count(&args, ASYNC_FLAG).is_some();
+    if runs_async && scenario.chat.capacity.is_some() && scenario.chat.overflow == Overflow::Block {
+        eprintln!("Students running async cannot wait for room in the chat, use {} with another policy", OVERFLOW_FLAG);
+        usage();
+    }
+    if args.iter().any(|arg| arg == COMPARE_FLAG) {
+        compare(&args, scenario);
+        return;
+    }
+    let execution = match (flag_count(&args, POOL_FLAG), flag_count(&args, ASYNC_FLAG)) {
+        (Some(_), Some(_)) => usage(),
+        (Some(workers), None) => Execution::Pool { workers },
+        (None, Some(threads)) => Execution::Async { threads },
+        (None, None) => Execution::ThreadPerStudent,
+    };
+
     println!();
     println!("{:?}: Start of training session", thread::current().id());
 
-    let (tx, rx) = mpsc::channel();
-    let mut thread_handles = vec![];
-
-    thread::spawn(|| {
-        let chat = ChatScreen {
-            rx,
-        };
-        chat.handle_messages();
-    });
-
-    let handle = thread::spawn(|| {
-        // Disclaimer: Any resemblance to real persons is purely coincidental!
-        let presenter = Presenter::new("Mat");
-        presenter.present();
-    });
-    thread_handles.push(handle);
-
-    for idx in 0..NUMBER_OF_STUDENTS {
-        let tx_clone = mpsc::Sender::clone(&tx);
-        // A move closure transfers ownership of values from one thread to another.
-        // Needed to be able to use variable idx (declared in main thread) in each spawned thread.
-        let handle = thread::spawn(move || {
-            let student = Student::new(format!("{}{}", "Student_", idx + 1).as_str(), tx_clone);
-            student.active_listen();
-        });
-        thread_handles.push(handle);
+    let record = session::run_session(scenario, new_clock(&args), execution);
+    let transcript = &record.transcript;
+
+    // files are saved before the summary, which ends the output, and any that could not be are reported after it
+    let exports = [
+        (TRANSCRIPT_FLAG, export::json_lines(&transcript.events)),
+        (MINUTES_FLAG, export::minutes(&transcript.events)),
+        (METRICS_FLAG, serde_json::to_string_pretty(&record.metrics).expect("metrics can always be serialized")),
+    ];
+    let mut written = vec![];
+    let mut failed = vec![];
+    for (flag, contents) in exports.iter() {
+        if let Some(path) = flag_value(&args, flag) {
+            match fs::write(path, contents) {
+                Ok(()) => written.push(path),
+                Err(e) => failed.push(format!("could not write {}: {}", path, e)),
+            }
+        }
     }
 
-    // block the main thread until all threads have finished (except the chat, which never finishes)
-    for handle in thread_handles {
-        handle.join().unwrap();
+    println!("{}", record.summary(&written));
+    if !failed.is_empty() {
+        for error in failed {
+            eprintln!("{}", error);
+        }
+        exit(1);
     }
-
-    println!();
-    println!("{:?}: End of training session", thread::current().id());
 }
@@ -0,0 +1,245 @@
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::events::Event;
+use crate::queues::Overflow;
+use crate::session::Transcript;
+
+// How long every participant waited to be able to speak, in total
+#[derive(Default)]
+pub(crate) struct SpeakWaits {
+    // in order of their first time
+    totals: Mutex<Vec<(String, Duration)>>,
+}
+
+impl SpeakWaits {
+    pub(crate) fn record(&self, name: &str, waited: Duration) {
+        let mut totals = self.totals.lock().unwrap();
+        match totals.iter_mut().find(|(who, _)| who == name) {
+            Some((_, total)) => *total += waited,
+            None => totals.push((String::from(name), waited)),
+        }
+    }
+
+    fn total(&self, name: &str) -> Duration {
+        let totals = self.totals.lock().unwrap();
+        totals.iter().find(|(who, _)| who == name).map_or(Duration::from_secs(0), |(_, total)| *total)
+    }
+}
+
+// How long every chat message took to reach the chat screen, in the order it got them
+#[derive(Default)]
+pub(crate) struct ChatLatencies {
+    latencies: Mutex<Vec<chrono::Duration>>,
+}
+
+impl ChatLatencies {
+    pub(crate) fn record(&self, sent_at: DateTime<Utc>, received_at: DateTime<Utc>) {
+        self.latencies.lock().unwrap().push(received_at - sent_at);
+    }
+
+    pub(crate) fn measure(&self) -> LatencyMetrics {
+        let latencies: Vec<i64> = self.latencies.lock().unwrap().iter()
+            .map(|latency| latency.num_microseconds().unwrap_or(i64::MAX))
+            .collect();
+        LatencyMetrics {
+            messages: latencies.len(),
+            mean_us: if latencies.is_empty() { 0 } else { latencies.iter().sum::<i64>() / latencies.len() as i64 },
+            max_us: latencies.iter().copied().max().unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ParticipantMetrics {
+    pub(crate) name: String,
+    pub(crate) chat_messages: usize,
+    pub(crate) reactions: usize,
+    pub(crate) questions: usize,
+    pub(crate) times_spoken: usize,
+    pub(crate) speak_wait_us: u64,
+}
+
+// From the moment a chat message was sent until the chat screen got it, on the session clock
+#[derive(Debug, Serialize)]
+pub(crate) struct LatencyMetrics {
+    pub(crate) messages: usize,
+    pub(crate) mean_us: i64,
+    pub(crate) max_us: i64,
+}
+
+// What a bounded chat could not take as it came
+#[derive(Debug, Serialize)]
+pub(crate) struct OverflowMetrics {
+    pub(crate) capacity: usize,
+    pub(crate) policy: Overflow,
+    pub(crate) dropped: usize,
+    pub(crate) coalesced: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ConceptMetrics {
+    pub(crate) title: String,
+    pub(crate) duration_ms: i64,
+    // by at least one student
+    pub(crate) found_tricky: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct SessionMetrics {
+    pub(crate) participants: Vec<ParticipantMetrics>,
+    pub(crate) chat_latency: LatencyMetrics,
+    // with a bounded chat only
+    pub(crate) chat_overflow: Option<OverflowMetrics>,
+    pub(crate) concepts: Vec<ConceptMetrics>,
+    pub(crate) tricky_fraction: f64,
+}
+
+impl SessionMetrics {
+    pub(crate) fn measure(transcript: &Transcript, speak_waits: &SpeakWaits, chat_latencies: &ChatLatencies,
+                          chat_overflow: Option<OverflowMetrics>) -> Self {
+        let mut participants: Vec<ParticipantMetrics> = vec![];
+        let mut concepts: Vec<ConceptMetrics> = vec![];
+        let mut concept_started = None;
+        for event in &transcript.events {
+            match event {
+                Event::Joined { name, .. } => participants.push(ParticipantMetrics {
+                    name: name.clone(),
+                    chat_messages: 0,
+                    reactions: 0,
+                    questions: 0,
+                    times_spoken: 0,
+                    speak_wait_us: speak_waits.total(name).as_micros() as u64,
+                }),
+                Event::ConceptStarted { timestamp, .. } => concept_started = Some(*timestamp),
+                Event::ConceptEnded { title, timestamp } => concepts.push(ConceptMetrics {
+                    title: title.clone(),
+                    duration_ms: concept_started.map_or(0, |started| (*timestamp - started).num_milliseconds()),
+                    found_tricky: false,
+                }),
+                Event::ChatMessage(msg) => {
+                    if let Some(p) = participant(&mut participants, &msg.username) {
+                        p.chat_messages += 1;
+                    }
+                }
+                Event::Reaction(msg) => {
+                    if let Some(p) = participant(&mut participants, &msg.username) {
+                        p.reactions += 1;
+                    }
+                }
+                Event::Spoke(utterance) => {
+                    if let Some(p) = participant(&mut participants, &utterance.name) {
+                        p.times_spoken += 1;
+                    }
+                }
+                Event::Question(question) => {
+                    if let Some(p) = participant(&mut participants, &question.student) {
+                        p.questions += 1;
+                    }
+                }
+                Event::Left { .. } | Event::Answered(_) | Event::RoomsOpened { .. } | Event::RoomClosed { .. } => {}
+            }
+        }
+        // questions can be asked about a concept before or after the presenter is done with it
+        for question in transcript.events.iter().filter_map(|event| match event {
+            Event::Question(question) => Some(question),
+            _ => None,
+        }) {
+            if let Some(concept) = concepts.iter_mut().find(|c| c.title == question.concept) {
+                concept.found_tricky = true;
+            }
+        }
+        let tricky = concepts.iter().filter(|c| c.found_tricky).count();
+        Self {
+            participants,
+            chat_latency: chat_latencies.measure(),
+            chat_overflow,
+            tricky_fraction: if concepts.is_empty() { 0.0 } else { tricky as f64 / concepts.len() as f64 },
+            concepts,
+        }
+    }
+
+    // A few tables, ready to print
+    pub(crate) fn report(&self) -> String {
+        let mut report = String::new();
+        // writing to a String never fails
+        let width = self.participants.iter().map(|p| p.name.len()).chain(Some("Participant".len())).max().unwrap();
+        writeln!(report, "{:<width$}  {:>5}  {:>9}  {:>9}  {:>5}  {:>15}", "Participant", "Chat", "Reactions",
+                 "Questions", "Spoke", "Waited to speak", width = width).unwrap();
+        for p in &self.participants {
+            writeln!(report, "{:<width$}  {:>5}  {:>9}  {:>9}  {:>5}  {:>13.3}ms", p.name, p.chat_messages,
+                     p.reactions, p.questions, p.times_spoken, p.speak_wait_us as f64 / 1000.0, width = width).unwrap();
+        }
+        writeln!(report).unwrap();
+        writeln!(report, "Chat delivery latency: {} messages, mean {:.3}ms, max {:.3}ms", self.chat_latency.messages,
+                 self.chat_latency.mean_us as f64 / 1000.0, self.chat_latency.max_us as f64 / 1000.0).unwrap();
+        if let Some(overflow) = &self.chat_overflow {
+            writeln!(report, "Chat overflow (capacity {}, {}): {} dropped, {} coalesced", overflow.capacity,
+                     overflow.policy, overflow.dropped, overflow.coalesced).unwrap();
+        }
+        writeln!(report).unwrap();
+        let width = self.concepts.iter().map(|c| c.title.len()).chain(Some("Concept".len())).max().unwrap();
+        writeln!(report, "{:<width$}  {:>8}  Found tricky", "Concept", "Duration", width = width).unwrap();
+        for c in &self.concepts {
+            writeln!(report, "{:<width$}  {:>6}ms  {}", c.title, c.duration_ms,
+                     if c.found_tricky { "yes" } else { "no" }, width = width).unwrap();
+        }
+        writeln!(report).unwrap();
+        write!(report, "{:.0}% of the concepts were found tricky", self.tricky_fraction * 100.0).unwrap();
+        report
+    }
+}
+
+fn participant<'a>(participants: &'a mut [ParticipantMetrics], name: &str) -> Option<&'a mut ParticipantMetrics> {
+    participants.iter_mut().find(|p| p.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use crate::chat::ChatMessage;
+    use crate::questions::Question;
+
+    use super::*;
+
+    fn at(ms: i64) -> DateTime<Utc> {
+        Utc.ymd(2020, 1, 1).and_hms(9, 0, 0) + chrono::Duration::milliseconds(ms)
+    }
+
+    #[test]
+    fn measures_what_happened() {
+        let hi = ChatMessage::new("Ana", "Hi!", at(100));
+        let me = ChatMessage::reply("Ana", "Me!", at(200), &hi);
+        let events = vec![
+            Event::Joined { name: String::from("Ana"), timestamp: at(0) },
+            Event::ConceptStarted { title: String::from("Traits"), timestamp: at(0) },
+            Event::ChatMessage(hi.clone()),
+            Event::Reaction(me.clone()),
+            Event::ConceptEnded { title: String::from("Traits"), timestamp: at(1500) },
+            Event::ConceptStarted { title: String::from("Lifetimes"), timestamp: at(1500) },
+            Event::Question(Question::new("Ana", "Lifetimes", at(1600))),
+            Event::ConceptEnded { title: String::from("Lifetimes"), timestamp: at(3500) },
+        ];
+        let speak_waits = SpeakWaits::default();
+        speak_waits.record("Ana", Duration::from_micros(5));
+        speak_waits.record("Ana", Duration::from_micros(7));
+        let chat_latencies = ChatLatencies::default();
+        chat_latencies.record(hi.timestamp, at(102));
+        chat_latencies.record(me.timestamp, at(204));
+
+        let metrics = SessionMetrics::measure(&Transcript { events }, &speak_waits, &chat_latencies, None);
+        let ana = &metrics.participants[0];
+        assert_eq!((ana.chat_messages, ana.reactions, ana.questions, ana.speak_wait_us), (1, 1, 1, 12));
+        assert_eq!((metrics.chat_latency.messages, metrics.chat_latency.mean_us, metrics.chat_latency.max_us),
+                   (2, 3000, 4000));
+        let concepts: Vec<_> = metrics.concepts.iter().map(|c| (c.title.as_str(), c.duration_ms, c.found_tricky))
+            .collect();
+        assert_eq!(concepts, vec![("Traits", 1500, false), ("Lifetimes", 2000, true)]);
+        assert_eq!(metrics.tricky_fraction, 0.5);
+    }
+}
@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::chat::ChatMessage;
+use crate::scenario::FilterConfig;
+
+// Something the chat screen puts every message through before showing it. A filter can change the message or
+// hold it back, and says so, for moderators to find out.
+pub(crate) trait ChatFilter: Send {
+    fn name(&self) -> &'static str;
+
+    // What the filter did to the message, if anything
+    fn apply(&mut self, msg: &mut ChatMessage) -> Option<Action>;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Action {
+    // kept off the screen, and away from any filter after this one
+    Held(String),
+    Edited(String),
+}
+
+// Every time a filter did something to a message
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ModeratorAction {
+    pub(crate) username: String,
+    pub(crate) sent_at: DateTime<Utc>,
+    pub(crate) filter: &'static str,
+    pub(crate) action: Action,
+}
+
+// Filters, one after the other, and the log of everything they did
+#[derive(Default)]
+pub(crate) struct ChatPipeline {
+    filters: Vec<Box<dyn ChatFilter>>,
+    log: Vec<ModeratorAction>,
+}
+
+impl ChatPipeline {
+    // Every filter set in the config, rate limiting first and cutting messages down to length last
+    pub(crate) fn new(config: &FilterConfig) -> Self {
+        let mut pipeline = ChatPipeline::default();
+        if let Some(rate_limit) = &config.rate_limit {
+            pipeline = pipeline.with(RateLimit::new(rate_limit.per_second, rate_limit.burst));
+        }
+        if let Some(window_ms) = config.duplicate_window_ms {
+            pipeline = pipeline.with(Duplicates::new(chrono::Duration::milliseconds(window_ms as i64)));
+        }
+        if !config.banned_words.is_empty() {
+            pipeline = pipeline.with(BannedWords::new(&config.banned_words));
+        }
+        if let Some(max) = config.max_length {
+            pipeline = pipeline.with(MaxLength::new(max));
+        }
+        pipeline
+    }
+
+    pub(crate) fn with(mut self, filter: impl ChatFilter + 'static) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    // The message as it is to be shown, if at all
+    pub(crate) fn process(&mut self, mut msg: ChatMessage) -> Option<ChatMessage> {
+        for filter in self.filters.iter_mut() {
+            if let Some(action) = filter.apply(&mut msg) {
+                let held = matches!(action, Action::Held(_));
+                self.log.push(ModeratorAction {
+                    username: msg.username.clone(),
+                    sent_at: msg.timestamp,
+                    filter: filter.name(),
+                    action,
+                });
+                if held {
+                    return None;
+                }
+            }
+        }
+        Some(msg)
+    }
+
+    pub(crate) fn log(&self) -> &[ModeratorAction] {
+        &self.log
+    }
+}
+
+// A token bucket for every user: it holds up to burst messages, refills at per_second messages a second, as
+// the messages' own timestamps tell, and every message takes one out
+pub(crate) struct RateLimit {
+    per_second: f64,
+    burst: f64,
+    // tokens left and when they were counted
+    buckets: HashMap<String, (f64, DateTime<Utc>)>,
+}
+
+impl RateLimit {
+    pub(crate) fn new(per_second: f64, burst: u32) -> Self {
+        Self {
+            per_second,
+            burst: f64::from(burst),
+            buckets: HashMap::new(),
+        }
+    }
+}
+
+impl ChatFilter for RateLimit {
+    fn name(&self) -> &'static str {
+        "rate limit"
+    }
+
+    fn apply(&mut self, msg: &mut ChatMessage) -> Option<Action> {
+        let (per_second, burst) = (self.per_second, self.burst);
+        let (tokens, counted_at) = self.buckets.entry(msg.username.clone()).or_insert((burst, msg.timestamp));
+        let elapsed = (msg.timestamp - *counted_at).num_microseconds().unwrap_or(i64::MAX).max(0) as f64 / 1e6;
+        *tokens = (*tokens + elapsed * per_second).min(burst);
+        *counted_at = msg.timestamp;
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            None
+        } else {
+            Some(Action::Held(format!("more than {} messages a second", per_second)))
+        }
+    }
+}
+
+// Holds back a message its sender already sent, word for word, within the window
+pub(crate) struct Duplicates {
+    window: chrono::Duration,
+    // every user's last message and when they sent it
+    last: HashMap<String, (String, DateTime<Utc>)>,
+}
+
+impl Duplicates {
+    pub(crate) fn new(window: chrono::Duration) -> Self {
+        Self {
+            window,
+            last: HashMap::new(),
+        }
+    }
+}
+
+impl ChatFilter for Duplicates {
+    fn name(&self) -> &'static str {
+        "duplicates"
+    }
+
+    fn apply(&mut self, msg: &mut ChatMessage) -> Option<Action> {
+        let previous = self.last.insert(msg.username.clone(), (msg.text.clone(), msg.timestamp));
+        match previous {
+            Some((text, sent_at)) if text == msg.text && msg.timestamp - sent_at <= self.window => {
+                Some(Action::Held(format!("sent {}ms before", (msg.timestamp - sent_at).num_milliseconds())))
+            }
+            _ => None,
+        }
+    }
+}
+
+// Masks every banned word with as many asterisks, whatever the case. Only whole words are, so that banning
+// "ass" does not mask "class".
+pub(crate) struct BannedWords {
+    // in lower case
+    words: Vec<String>,
+}
+
+impl BannedWords {
+    pub(crate) fn new(words: &[String]) -> Self {
+        Self {
+            words: words.iter().map(|word| word.to_lowercase()).collect(),
+        }
+    }
+}
+
+impl ChatFilter for BannedWords {
+    fn name(&self) -> &'static str {
+        "banned words"
+    }
+
+    fn apply(&mut self, msg: &mut ChatMessage) -> Option<Action> {
+        let mut masked = String::with_capacity(msg.text.len());
+        let mut word = String::new();
+        let mut banned = 0;
+        // a space at the end to finish the last word
+        for c in msg.text.chars().chain(Some(' ')) {
+            if c.is_alphanumeric() {
+                word.push(c);
+                continue;
+            }
+            if self.words.contains(&word.to_lowercase()) {
+                masked.extend(word.chars().map(|_| '*'));
+                banned += 1;
+            } else {
+                masked.push_str(&word);
+            }
+            word.clear();
+            masked.push(c);
+        }
+        masked.pop();
+        if banned == 0 {
+            return None;
+        }
+        msg.text = masked;
+        Some(Action::Edited(format!("masked {} banned word{}", banned, if banned == 1 { "" } else { "s" })))
+    }
+}
+
+// Cuts messages down to max characters
+pub(crate) struct MaxLength {
+    max: usize,
+}
+
+impl MaxLength {
+    pub(crate) fn new(max: usize) -> Self {
+        Self { max }
+    }
+}
+
+impl ChatFilter for MaxLength {
+    fn name(&self) -> &'static str {
+        "max length"
+    }
+
+    fn apply(&mut self, msg: &mut ChatMessage) -> Option<Action> {
+        let length = msg.text.chars().count();
+        if length <= self.max {
+            return None;
+        }
+        msg.text = msg.text.chars().take(self.max).collect();
+        Some(Action::Edited(format!("cut down from {} characters", length)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(ms: i64) -> DateTime<Utc> {
+        Utc.ymd(2020, 1, 1).and_hms(9, 0, 0) + chrono::Duration::milliseconds(ms)
+    }
+
+    // What the filter did to every message, and what was left of the ones it let through
+    fn run(filter: &mut dyn ChatFilter, messages: &[(&str, &str, i64)]) -> Vec<(Option<Action>, String)> {
+        messages.iter().map(|(username, text, ms)| {
+            let mut msg = ChatMessage::new(username, text, at(*ms));
+            let action = filter.apply(&mut msg);
+            (action, msg.text)
+        }).collect()
+    }
+
+    fn held(actions: &[(Option<Action>, String)]) -> Vec<bool> {
+        actions.iter().map(|(action, _)| matches!(action, Some(Action::Held(_)))).collect()
+    }
+
+    #[test]
+    fn everybody_gets_a_burst_of_messages_and_then_as_many_as_the_rate() {
+        let mut rate_limit = RateLimit::new(1.0, 2);
+        let actions = run(&mut rate_limit, &[("Ana", "1", 0), ("Ana", "2", 100), ("Ana", "3", 200), ("Bob", "1", 300),
+                                             ("Ana", "4", 1100), ("Ana", "5", 1200), ("Ana", "6", 5000)]);
+        assert_eq!(held(&actions), vec![false, false, true, false, false, true, false]);
+    }
+
+    #[test]
+    fn the_same_message_is_only_shown_again_once_the_window_is_over() {
+        let mut duplicates = Duplicates::new(chrono::Duration::milliseconds(1000));
+        let actions = run(&mut duplicates, &[("Ana", "hi", 0), ("Bob", "hi", 100), ("Ana", "hi", 500),
+                                             ("Ana", "hi", 1600), ("Ana", "bye", 1700), ("Ana", "hi", 1800)]);
+        assert_eq!(held(&actions), vec![false, false, true, false, false, false]);
+    }
+
+    #[test]
+    fn banned_words_are_masked_whatever_the_case_but_only_as_whole_words() {
+        let mut banned = BannedWords::new(&[String::from("Boring"), String::from("ass")]);
+        let actions = run(&mut banned, &[("Ana", "BORING, so boring!", 0), ("Bob", "What a class", 0)]);
+        assert_eq!(actions, vec![
+            (Some(Action::Edited(String::from("masked 2 banned words"))), String::from("******, so ******!")),
+            (None, String::from("What a class")),
+        ]);
+    }
+
+    #[test]
+    fn long_messages_are_cut_down() {
+        let mut max_length = MaxLength::new(5);
+        let actions = run(&mut max_length, &[("Ana", "héllo", 0), ("Ana", "héllo wörld", 0)]);
+        assert_eq!(actions, vec![
+            (None, String::from("héllo")),
+            (Some(Action::Edited(String::from("cut down from 11 characters"))), String::from("héllo")),
+        ]);
+    }
+
+    #[test]
+    fn the_pipeline_logs_every_action_and_stops_at_the_first_message_held() {
+        let mut pipeline = ChatPipeline::default()
+            .with(Duplicates::new(chrono::Duration::milliseconds(1000)))
+            .with(BannedWords::new(&[String::from("boring")]))
+            .with(MaxLength::new(8));
+        let shown = pipeline.process(ChatMessage::new("Ana", "boring stuff", at(0)));
+        assert_eq!(shown.map(|msg| msg.text), Some(String::from("****** s")));
+        assert_eq!(pipeline.process(ChatMessage::new("Ana", "boring stuff", at(10))), None);
+
+        let log: Vec<_> = pipeline.log().iter().map(|action| (action.username.as_str(), action.filter)).collect();
+        assert_eq!(log, vec![("Ana", "banned words"), ("Ana", "max length"), ("Ana", "duplicates")]);
+    }
+}
@@ -0,0 +1,561 @@
+use std::future;
+use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+
+use crate::board::{ConceptBoard, Cue};
+use crate::breakout::Breakout;
+use crate::broadcast::TryRecvError as FeedError;
+use crate::bus::{EventSender, Feed};
+use crate::chat::ChatMessage;
+use crate::clock::{ActorId, Clock};
+use crate::direct::{Delivery, DirectMessage, Mailbox};
+use crate::events::Event;
+use crate::executor;
+use crate::floor::Floor;
+use crate::metrics::SpeakWaits;
+use crate::questions::{Answer, Question};
+use crate::scenario::{Concept, PresenterConfig, StudentProfile};
+
+// How often the presenter checks for late questions once every concept has been presented
+const QUESTION_POLL: Duration = Duration::from_millis(100);
+
+// How often participants waiting to speak check whether it is their turn
+const FLOOR_POLL: Duration = Duration::from_millis(100);
+
+// Something said out loud during the session
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct Utterance {
+    pub(crate) name: String,
+    pub(crate) text: String,
+    pub(crate) timestamp: DateTime<Utc>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum FloorAttempt {
+    Taken,
+    GaveUp,
+    Waiting,
+}
+
+// What every participant shares: the session clock, the event bus, the concept board, the floor and how long
+// they waited for it, and the breakout rooms. The bus closes once the last participant has left the stage.
+pub(crate) struct Stage {
+    pub(crate) clock: Arc<dyn Clock>,
+    events: EventSender,
+    board: ConceptBoard,
+    floor: Arc<Floor>,
+    speak_waits: Arc<SpeakWaits>,
+    breakout: Breakout,
+}
+
+impl Stage {
+    pub(crate) fn new(clock: Arc<dyn Clock>, events: EventSender, board: ConceptBoard, floor: Arc<Floor>,
+                      speak_waits: Arc<SpeakWaits>, breakout: Breakout) -> Self {
+        Self {
+            clock,
+            events,
+            board,
+            floor,
+            speak_waits,
+            breakout,
+        }
+    }
+
+    pub(crate) fn publish(&self, event: Event) {
+        self.events.send(event);
+    }
+
+    // Never blocks: whoever raised their hand at waiting_since tries again every FLOOR_POLL until it is their turn,
+    // or until they give up after patience
+    fn try_take_floor(&self, name: &str, waiting_since: DateTime<Utc>, patience: Option<Duration>) -> FloorAttempt {
+        // a real clock may go back in time, making it look like no time was waited at all
+        let waited = (self.clock.now() - waiting_since).to_std().unwrap_or_default();
+        if self.floor.try_take(name, self.clock.now()) {
+            self.speak_waits.record(name, waited);
+            FloorAttempt::Taken
+        } else if patience.is_some_and(|patience| waited >= patience) {
+            self.floor.lower_hand(name);
+            self.speak_waits.record(name, waited);
+            FloorAttempt::GaveUp
+        } else {
+            FloorAttempt::Waiting
+        }
+    }
+
+    fn release_floor(&self, name: &str) {
+        if !self.floor.release(name, self.clock.now()) {
+            println!("{:?} - {} was cut off for speaking for too long", thread::current().id(), name);
+        }
+    }
+
+    // Only for whoever holds the floor
+    fn say_stuff(&self, stuff: &str, name: &str) {
+        println!("{:?} - {} says: {}", thread::current().id(), name, stuff);
+        self.publish(Event::Spoke(Utterance {
+            name: String::from(name),
+            text: String::from(stuff),
+            timestamp: self.clock.now(),
+        }));
+    }
+
+    // Says stuff if it is our turn, giving the floor back straight away
+    fn try_speak(&self, stuff: &str, name: &str, waiting_since: DateTime<Utc>, patience: Duration) -> FloorAttempt {
+        let attempt = self.try_take_floor(name, waiting_since, Some(patience));
+        if attempt == FloorAttempt::Taken {
+            self.say_stuff(stuff, name);
+            self.release_floor(name);
+        }
+        attempt
+    }
+
+    pub(crate) fn is_given(&self, cue: Cue) -> bool {
+        self.board.is_given(cue)
+    }
+
+    async fn until(&self, cue: Cue) {
+        future::poll_fn(|cx| self.board.poll_for(cue, cx)).await
+    }
+
+    fn join(&self, name: &str) {
+        self.publish(Event::Joined { name: String::from(name), timestamp: self.clock.now() });
+    }
+
+    fn leave(&self, name: &str) {
+        self.publish(Event::Left { name: String::from(name), timestamp: self.clock.now() });
+    }
+}
+
+// Presenter and students alike go through the session one step at a time, never blocking in between: every step
+// does whatever there is to do right now, and tells what to wait for before the next one. Whoever runs a
+// participant must not take the next step before then.
+pub(crate) trait Participant: Send {
+    fn step(&mut self) -> Step;
+    fn stage(&self) -> &Arc<Stage>;
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Step {
+    Sleep(Duration),
+    WaitFor(Cue),
+    Done,
+}
+
+// On a thread of its own, blocking in between steps
+pub(crate) fn attend(mut participant: impl Participant, actor: ActorId) {
+    let stage = Arc::clone(participant.stage());
+    stage.clock.start(actor);
+    loop {
+        match participant.step() {
+            Step::Sleep(duration) => stage.clock.sleep(actor, duration),
+            Step::WaitFor(cue) => stage.board.wait_for(actor, cue),
+            Step::Done => break,
+        }
+    }
+    // leave (dropping every sender) before giving up the turn, so that others know it right away
+    drop(participant);
+    stage.clock.finish(actor);
+}
+
+// As a task on an executor, awaiting in between steps
+pub(crate) async fn attend_async(mut participant: impl Participant) {
+    let stage = Arc::clone(participant.stage());
+    loop {
+        match participant.step() {
+            Step::Sleep(duration) => executor::sleep(duration).await,
+            Step::WaitFor(cue) => stage.until(cue).await,
+            Step::Done => break,
+        }
+    }
+}
+
+// Goes through whatever private messages arrived for name, returning the ones actually meant for them
+fn read_mailbox(name: &str, mailbox: &Mailbox) -> Vec<DirectMessage> {
+    let tid = thread::current().id();
+    mailbox.receive().into_iter().filter_map(|delivery| match delivery {
+        Delivery::Message(msg) => {
+            println!("{:?} - {} got a private message from {}: {}", tid, name, msg.from, msg.text);
+            Some(msg)
+        }
+        Delivery::Undeliverable(msg, reason) => {
+            println!("{:?} - {}'s private message to {} went nowhere: {}", tid, name, msg.to, reason);
+            None
+        }
+    }).collect()
+}
+
+pub(crate) struct Presenter {
+    config: PresenterConfig,
+    concepts: Arc<Vec<Concept>>,
+    questions: Receiver<Question>,
+    mailbox: Mailbox,
+    stage: Arc<Stage>,
+    phase: PresenterPhase,
+}
+
+// Where the presenter is at in between steps. Questions are only taken between concepts (idx being the last one
+// presented), and once done presenting until every student has left.
+enum PresenterPhase {
+    Joining,
+    WaitingForFloor { since: DateTime<Utc>, turn: Turn },
+    Presenting(usize),
+    AwaitingReactions(usize),
+    Answering(usize),
+    // the class is in breakout rooms, and the next concept is idx
+    InBreakout(usize),
+    LastQuestions,
+    Left,
+}
+
+// What the presenter takes the floor for
+enum Turn {
+    Concept(usize),
+    Answer { question: Question, after: usize },
+}
+
+impl Presenter {
+    pub(crate) fn new(config: PresenterConfig, concepts: Arc<Vec<Concept>>, questions: Receiver<Question>,
+                      mailbox: Mailbox, stage: Arc<Stage>) -> Self {
+        Self {
+            config,
+            concepts,
+            questions,
+            mailbox,
+            stage,
+            phase: PresenterPhase::Joining,
+        }
+    }
+
+    // In between concepts, private messages get a private answer
+    fn read_mailbox(&self) {
+        for msg in read_mailbox(&self.config.name, &self.mailbox) {
+            self.mailbox.send(&msg.from, "Thanks for letting me know!", self.stage.clock.now());
+        }
+    }
+
+    // Raises a hand, to present a concept or answer a question
+    fn take_floor(&mut self, turn: Turn) -> Step {
+        let since = self.stage.clock.now();
+        self.stage.floor.raise_hand(&self.config.name);
+        self.phase = PresenterPhase::WaitingForFloor { since, turn };
+        self.try_take_floor(since)
+    }
+
+    fn try_take_floor(&mut self, since: DateTime<Utc>) -> Step {
+        if self.stage.try_take_floor(&self.config.name, since, None) == FloorAttempt::Waiting {
+            return Step::Sleep(FLOOR_POLL);
+        }
+        let clock = &self.stage.clock;
+        match std::mem::replace(&mut self.phase, PresenterPhase::Left) {
+            PresenterPhase::WaitingForFloor { turn: Turn::Concept(idx), .. } => {
+                // the presenter holds the floor for the whole concept
+                let title = &self.concepts[idx].title;
+                self.stage.publish(Event::ConceptStarted { title: title.clone(), timestamp: clock.now() });
+                self.stage.say_stuff(format!("Let me tell you about {}...", title).as_str(), &self.config.name);
+                self.stage.board.announce(idx);
+                self.phase = PresenterPhase::Presenting(idx);
+                Step::Sleep(self.config.pace())
+            }
+            // every student hears the answer, as it goes through the event bus
+            PresenterPhase::WaitingForFloor { turn: Turn::Answer { question, after }, .. } => {
+                self.stage.say_stuff(format!("{} asked about {}, let me explain it again...", question.student,
+                                             question.concept).as_str(),
+                                     &self.config.name);
+                self.stage.publish(Event::Answered(Answer {
+                    question,
+                    presenter: self.config.name.clone(),
+                    answered_at: clock.now(),
+                }));
+                self.phase = PresenterPhase::Answering(after);
+                Step::Sleep(self.config.answer_time())
+            }
+            _ => unreachable!("only taking the floor while waiting for it"),
+        }
+    }
+
+    fn next_concept(&mut self, idx: usize) -> Step {
+        if self.stage.breakout.is_after(idx) && !self.stage.is_given(Cue::RoomsOpened) {
+            return self.open_rooms(idx);
+        }
+        if idx < self.concepts.len() {
+            self.take_floor(Turn::Concept(idx))
+        } else {
+            self.take_last_questions()
+        }
+    }
+
+    // Sends the class to breakout rooms, and waits for the discussion to be over before going on with idx
+    fn open_rooms(&mut self, idx: usize) -> Step {
+        let rooms = self.stage.breakout.open();
+        println!("{:?} - {} sends everybody to {} breakout rooms", thread::current().id(), self.config.name,
+                 rooms.len());
+        self.stage.publish(Event::RoomsOpened { rooms, timestamp: self.stage.clock.now() });
+        self.stage.board.open_rooms();
+        self.phase = PresenterPhase::InBreakout(idx);
+        Step::Sleep(self.stage.breakout.duration())
+    }
+
+    // Brings everybody back, and puts what was said in every room on record
+    fn close_rooms(&mut self, idx: usize) -> Step {
+        println!("{:?} - {} brings everybody back from the breakout rooms", thread::current().id(),
+                 self.config.name);
+        for (room, (members, conversation)) in self.stage.breakout.close().into_iter().enumerate() {
+            self.stage.publish(Event::RoomClosed { room: room + 1, members, conversation,
+                                                   timestamp: self.stage.clock.now() });
+        }
+        self.next_concept(idx)
+    }
+
+    fn take_questions(&mut self, after: usize) -> Step {
+        self.read_mailbox();
+        match self.questions.try_recv() {
+            Ok(question) => self.take_floor(Turn::Answer { question, after }),
+            Err(_) => self.next_concept(after + 1),
+        }
+    }
+
+    // Every student owns a sender for questions, so the channel is only disconnected once they have all left.
+    // Until then somebody may still be confused about the last concepts.
+    fn take_last_questions(&mut self) -> Step {
+        self.read_mailbox();
+        match self.questions.try_recv() {
+            Ok(question) => self.take_floor(Turn::Answer { question, after: self.concepts.len() }),
+            Err(TryRecvError::Empty) => {
+                self.phase = PresenterPhase::LastQuestions;
+                Step::Sleep(QUESTION_POLL)
+            }
+            Err(TryRecvError::Disconnected) => {
+                self.stage.leave(&self.config.name);
+                self.phase = PresenterPhase::Left;
+                Step::Done
+            }
+        }
+    }
+}
+
+impl Participant for Presenter {
+    fn step(&mut self) -> Step {
+        match self.phase {
+            PresenterPhase::Joining => {
+                self.stage.join(&self.config.name);
+                self.next_concept(0)
+            }
+            PresenterPhase::WaitingForFloor { since, .. } => self.try_take_floor(since),
+            PresenterPhase::Presenting(idx) => {
+                let title = self.concepts[idx].title.clone();
+                self.stage.publish(Event::ConceptEnded { title, timestamp: self.stage.clock.now() });
+                self.stage.release_floor(&self.config.name);
+                // with the floor free, so that students can speak up
+                if self.config.wait_for_reactions {
+                    self.phase = PresenterPhase::AwaitingReactions(idx);
+                    return Step::WaitFor(Cue::Reacted(idx));
+                }
+                self.take_questions(idx)
+            }
+            PresenterPhase::AwaitingReactions(idx) => self.take_questions(idx),
+            PresenterPhase::Answering(after) => {
+                self.stage.release_floor(&self.config.name);
+                if after < self.concepts.len() {
+                    self.take_questions(after)
+                } else {
+                    self.take_last_questions()
+                }
+            }
+            PresenterPhase::InBreakout(idx) => self.close_rooms(idx),
+            PresenterPhase::LastQuestions => self.take_last_questions(),
+            PresenterPhase::Left => Step::Done,
+        }
+    }
+
+    fn stage(&self) -> &Arc<Stage> {
+        &self.stage
+    }
+}
+
+pub(crate) struct Student {
+    profile: StudentProfile,
+    concepts: Arc<Vec<Concept>>,
+    // everything going through the event bus, as shown on the chat screen
+    feed: Feed,
+    questions: Sender<Question>,
+    mailbox: Mailbox,
+    // everybody else in the session, who the student can send private messages to
+    contacts: Vec<String>,
+    stage: Arc<Stage>,
+    // a generator of its own, as the student may move from thread to thread
+    generator: StdRng,
+    phase: StudentPhase,
+}
+
+// Where the student is at in between steps
+#[derive(Clone, Copy)]
+enum StudentPhase {
+    Joining,
+    // the concept has been announced, time to listen to it
+    Announced(usize),
+    // done taking the concept in, time to react to it
+    Listened(usize),
+    WaitingForFloor { concept: usize, since: DateTime<Utc> },
+    // in a breakout room, or on the way to one, having said that many things there, before the next concept
+    InRoom { next: usize, said: usize },
+    Left,
+}
+
+// What students say in breakout rooms about the last concept, one after the other
+const ROOM_TALK: [&str; 3] = ["What did you make of {}?", "I think I get {} now", "Let's ask about {} later"];
+
+impl Student {
+    pub(crate) fn new(profile: StudentProfile, concepts: Arc<Vec<Concept>>, feed: Feed,
+                      questions: Sender<Question>, mailbox: Mailbox, contacts: Vec<String>, stage: Arc<Stage>) -> Self {
+        Self {
+            profile,
+            concepts,
+            feed,
+            questions,
+            mailbox,
+            contacts,
+            stage,
+            generator: StdRng::from_entropy(),
+            phase: StudentPhase::Joining,
+        }
+    }
+
+    // The student's choices are drawn from this seed, rather than from entropy
+    pub(crate) fn with_seed(mut self, seed: u64) -> Self {
+        self.generator = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    // Nobody can react to a concept before it has been presented
+    fn await_concept(&mut self, idx: usize) -> Step {
+        if idx < self.concepts.len() {
+            self.phase = StudentPhase::Announced(idx);
+            Step::WaitFor(Cue::Announced(idx))
+        } else {
+            self.stage.leave(&self.profile.name);
+            self.phase = StudentPhase::Left;
+            Step::Done
+        }
+    }
+
+    fn react(&mut self, idx: usize) -> Step {
+        self.catch_up();
+        let concept = &self.concepts[idx];
+        // gen_bool(0.0) is always false and gen_bool(1.0) always true, so profiles can be deterministic too
+        if concept.tricky || self.generator.gen_bool(self.profile.confusion) {
+            let since = self.stage.clock.now();
+            self.stage.floor.raise_hand(&self.profile.name);
+            self.phase = StudentPhase::WaitingForFloor { concept: idx, since };
+            return self.speak_up(idx, since);
+        }
+        if self.generator.gen_bool(self.profile.chattiness) {
+            let message = ChatMessage::new(&self.profile.name, format!("{} is cool!", concept.title).as_str(),
+                                           self.stage.clock.now()).about(&concept.title);
+            self.stage.publish(Event::ChatMessage(message));
+        }
+        if self.generator.gen_bool(self.profile.whispering) {
+            if let Some(contact) = self.contacts.choose(&mut self.generator) {
+                self.mailbox.send(contact, format!("psst, {} was easy, wasn't it?", concept.title).as_str(),
+                                  self.stage.clock.now());
+            }
+        }
+        self.reacted(idx)
+    }
+
+    fn speak_up(&mut self, idx: usize, since: DateTime<Utc>) -> Step {
+        let title = &self.concepts[idx].title;
+        // waiting to speak for longer than it takes to listen to a concept would mean missing the next one
+        match self.stage.try_speak(format!("{} sounds tricky :-(", title).as_str(), self.profile.name.as_str(),
+                                   since, self.profile.pace()) {
+            FloorAttempt::Waiting => return Step::Sleep(FLOOR_POLL),
+            FloorAttempt::GaveUp => println!("{:?} - {} gave up waiting to speak, and just asks about {}",
+                                             thread::current().id(), self.profile.name, title),
+            FloorAttempt::Taken => {}
+        }
+        let question = Question::new(&self.profile.name, title, self.stage.clock.now());
+        self.stage.publish(Event::Question(question.clone()));
+        self.questions.send(question).unwrap();
+        self.reacted(idx)
+    }
+
+    fn reacted(&mut self, idx: usize) -> Step {
+        self.stage.board.reacted(idx);
+        if self.stage.breakout.is_after(idx + 1) {
+            self.phase = StudentPhase::InRoom { next: idx + 1, said: 0 };
+            return Step::WaitFor(Cue::RoomsOpened);
+        }
+        self.await_concept(idx + 1)
+    }
+
+    // Talks about the last concept in the student's breakout room every so often, until it is closed. A student
+    // who took too long to get there finds it closed already.
+    fn discuss(&mut self, next: usize, said: usize) -> Step {
+        let title = &self.concepts[next - 1].title;
+        let text = ROOM_TALK[said % ROOM_TALK.len()].replace("{}", title);
+        let message = ChatMessage::new(&self.profile.name, &text, self.stage.clock.now()).about(title);
+        if !self.stage.breakout.say(message) {
+            return self.await_concept(next);
+        }
+        self.phase = StudentPhase::InRoom { next, said: said + 1 };
+        Step::Sleep(self.profile.pace())
+    }
+
+    // Goes through everything that happened since last time, reacting to some of what others said in the chat.
+    // Reactions are not worth reacting to, otherwise the chat would never calm down. Private messages are only
+    // read.
+    fn catch_up(&mut self) {
+        read_mailbox(&self.profile.name, &self.mailbox);
+        loop {
+            match self.feed.try_recv() {
+                Ok(Event::ChatMessage(msg)) if msg.username != self.profile.name => {
+                    if self.generator.gen_bool(self.profile.reactiveness) {
+                        let text = format!("@{} me too!", msg.username);
+                        let reply = ChatMessage::reply(&self.profile.name, &text, self.stage.clock.now(), &msg);
+                        self.stage.publish(Event::Reaction(reply));
+                    }
+                }
+                Ok(Event::Answered(answer)) => {
+                    println!("{:?} - {} heard {} explain {} to {}", thread::current().id(), self.profile.name,
+                             answer.presenter, answer.question.concept, answer.question.student);
+                }
+                Ok(_) => {}
+                Err(FeedError::Lagged(missed)) => {
+                    println!("{:?} - {} could not keep up and missed {} events", thread::current().id(),
+                             self.profile.name, missed);
+                }
+                Err(FeedError::Empty) | Err(FeedError::Closed) => break,
+            }
+        }
+    }
+}
+
+impl Participant for Student {
+    fn step(&mut self) -> Step {
+        match self.phase {
+            StudentPhase::Joining => {
+                self.stage.join(&self.profile.name);
+                self.await_concept(0)
+            }
+            StudentPhase::Announced(idx) => {
+                // it takes a while to take a concept in
+                self.phase = StudentPhase::Listened(idx);
+                Step::Sleep(self.profile.pace())
+            }
+            StudentPhase::Listened(idx) => self.react(idx),
+            StudentPhase::WaitingForFloor { concept, since } => self.speak_up(concept, since),
+            StudentPhase::InRoom { next, said } => self.discuss(next, said),
+            StudentPhase::Left => Step::Done,
+        }
+    }
+
+    fn stage(&self) -> &Arc<Stage> {
+        &self.stage
+    }
+}
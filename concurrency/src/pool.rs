@@ -0,0 +1,108 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+// A fixed number of worker threads taking jobs, in order, from a shared queue.
+//
+// A job that panics does not take its worker down with it. Dropping the pool closes the queue: the workers
+// finish every job already in it, and the pool waits for them before going away.
+pub(crate) struct ThreadPool {
+    workers: Vec<JoinHandle<()>>,
+    jobs: Option<Sender<Job>>,
+}
+
+impl ThreadPool {
+    pub(crate) fn new(size: usize) -> Self {
+        assert!(size > 0, "a thread pool needs at least one worker");
+        let (jobs_tx, jobs_rx) = mpsc::channel::<Job>();
+        let jobs_rx = Arc::new(Mutex::new(jobs_rx));
+        let workers = (0..size).map(|_| {
+            let jobs_rx = Arc::clone(&jobs_rx);
+            thread::spawn(move || loop {
+                // the lock is only held while waiting for a job, not while running it
+                let job = jobs_rx.lock().unwrap().recv();
+                match job {
+                    Ok(job) => {
+                        // the panic is reported as usual, and whoever waits for the job finds out it never finished
+                        let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                    }
+                    Err(_) => break,
+                }
+            })
+        }).collect();
+        Self {
+            workers,
+            jobs: Some(jobs_tx),
+        }
+    }
+
+    pub(crate) fn execute<F: FnOnce() + Send + 'static>(&self, job: F) {
+        self.jobs.as_ref().expect("the queue is only closed on drop").send(Box::new(job)).unwrap();
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        drop(self.jobs.take());
+        for worker in self.workers.drain(..) {
+            worker.join().unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn dropping_the_pool_waits_for_every_queued_job() {
+        let done = Arc::new(AtomicUsize::new(0));
+        let pool = ThreadPool::new(3);
+        for _ in 0..20 {
+            let done = Arc::clone(&done);
+            pool.execute(move || {
+                thread::sleep(Duration::from_millis(5));
+                done.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        drop(pool);
+        assert_eq!(done.load(Ordering::SeqCst), 20);
+    }
+
+    #[test]
+    fn jobs_run_on_the_workers() {
+        let pool = ThreadPool::new(2);
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..10 {
+            let tx = tx.clone();
+            pool.execute(move || tx.send(thread::current().id()).unwrap());
+        }
+        drop(tx);
+        let mut threads: Vec<_> = rx.iter().collect();
+        assert_eq!(threads.len(), 10);
+        threads.sort_by_key(|id| format!("{:?}", id));
+        threads.dedup();
+        assert!(threads.len() <= 2);
+        assert!(!threads.contains(&thread::current().id()));
+    }
+
+    #[test]
+    fn a_panicking_job_does_not_stop_its_worker() {
+        let pool = ThreadPool::new(1);
+        let (tx, rx) = mpsc::channel();
+        let panicking_tx = tx.clone();
+        pool.execute(move || {
+            let _unused = panicking_tx;
+            panic!("this job fails");
+        });
+        pool.execute(move || tx.send("still working").unwrap());
+        // the failed job's sender was dropped while unwinding, so only the second job's message ever arrives
+        assert_eq!(rx.iter().collect::<Vec<_>>(), vec!["still working"]);
+    }
+}
@@ -0,0 +1,35 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+// Raised by a student who found a concept tricky
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct Question {
+    pub(crate) student: String,
+    pub(crate) concept: String,
+    pub(crate) asked_at: DateTime<Utc>,
+}
+
+// Broadcast by the presenter to every student
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct Answer {
+    pub(crate) question: Question,
+    pub(crate) presenter: String,
+    pub(crate) answered_at: DateTime<Utc>,
+}
+
+impl Question {
+    pub(crate) fn new(student: &str, concept: &str, asked_at: DateTime<Utc>) -> Self {
+        Self {
+            student: String::from(student),
+            concept: String::from(concept),
+            asked_at,
+        }
+    }
+}
+
+impl Answer {
+    // How long the question waited before the presenter got to it
+    pub(crate) fn waited(&self) -> Duration {
+        self.answered_at - self.question.asked_at
+    }
+}
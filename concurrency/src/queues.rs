@@ -0,0 +1,557 @@
+use std::cell::UnsafeCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+
+// More ways of getting messages from many senders to a single receiver, besides std's mpsc: a queue behind a
+// lock, bounded or not, and a lock-free ring buffer. They all work like mpsc as far as their users can tell:
+// senders are cloned, and the receiver finds out there is nothing more to come once every sender is gone.
+
+// A VecDeque behind a Mutex, with a Condvar for the receiver to wait on
+pub(crate) fn locked_channel<T>() -> (LockedSender<T>, LockedReceiver<T>) {
+    let shared = Arc::new(Locked {
+        state: Mutex::new(LockedState {
+            queue: VecDeque::new(),
+            senders: 1,
+        }),
+        available: Condvar::new(),
+    });
+    (LockedSender { shared: Arc::clone(&shared) }, LockedReceiver { shared })
+}
+
+pub(crate) struct LockedSender<T> {
+    shared: Arc<Locked<T>>,
+}
+
+pub(crate) struct LockedReceiver<T> {
+    shared: Arc<Locked<T>>,
+}
+
+struct Locked<T> {
+    state: Mutex<LockedState<T>>,
+    available: Condvar,
+}
+
+struct LockedState<T> {
+    queue: VecDeque<T>,
+    senders: usize,
+}
+
+impl<T> LockedSender<T> {
+    pub(crate) fn send(&self, value: T) {
+        self.shared.state.lock().unwrap().queue.push_back(value);
+        self.shared.available.notify_one();
+    }
+}
+
+impl<T> Clone for LockedSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().unwrap().senders += 1;
+        Self { shared: Arc::clone(&self.shared) }
+    }
+}
+
+impl<T> Drop for LockedSender<T> {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().senders -= 1;
+        self.shared.available.notify_one();
+    }
+}
+
+impl<T> LockedReceiver<T> {
+    // The next value, waiting for it if need be, or None once every sender is gone and every value is out
+    pub(crate) fn recv(&self) -> Option<T> {
+        let mut state = self.shared.state.lock().unwrap();
+        loop {
+            if let Some(value) = state.queue.pop_front() {
+                return Some(value);
+            }
+            if state.senders == 0 {
+                return None;
+            }
+            state = self.shared.available.wait(state).unwrap();
+        }
+    }
+}
+
+// What a bounded channel does with a message that finds it full
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Overflow {
+    // the sender waits for room
+    #[default]
+    Block,
+    DropNewest,
+    DropOldest,
+    // merged into the newest waiting message it can be merged with, or else dropped
+    Coalesce,
+}
+
+const OVERFLOWS: [(Overflow, &str); 4] = [(Overflow::Block, "block"), (Overflow::DropNewest, "drop-newest"),
+                                          (Overflow::DropOldest, "drop-oldest"), (Overflow::Coalesce, "coalesce")];
+
+impl FromStr for Overflow {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match OVERFLOWS.iter().find(|(_, known)| *known == name) {
+            Some((overflow, _)) => Ok(*overflow),
+            None => Err(format!("unknown overflow policy {}, it has to be one of: {}", name,
+                                OVERFLOWS.iter().map(|(_, known)| *known).collect::<Vec<_>>().join(", "))),
+        }
+    }
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (_, name) = OVERFLOWS.iter().find(|(overflow, _)| overflow == self).unwrap();
+        write!(f, "{}", name)
+    }
+}
+
+// Messages a bounded channel may drop, or merge, when full. Any other message gets in all the same, unless
+// senders are to wait for room, which they all do then.
+pub(crate) trait Expendable: Sized {
+    fn is_expendable(&self) -> bool;
+
+    // Takes a later message in, or gives it back if the two cannot be merged
+    fn absorb(&mut self, later: Self) -> Result<(), Self>;
+}
+
+// How many messages a bounded channel could not take as they came
+#[derive(Debug, Default)]
+pub(crate) struct OverflowCounts {
+    dropped: AtomicUsize,
+    coalesced: AtomicUsize,
+}
+
+impl OverflowCounts {
+    pub(crate) fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn coalesced(&self) -> usize {
+        self.coalesced.load(Ordering::Relaxed)
+    }
+}
+
+// A VecDeque behind a Mutex again, holding up to capacity messages. The receiver can wait for them on a thread,
+// or as a task.
+pub(crate) fn bounded_channel<T: Expendable>(capacity: usize, overflow: Overflow, counts: Arc<OverflowCounts>)
+                                             -> (BoundedSender<T>, BoundedReceiver<T>) {
+    assert!(capacity > 0, "a bounded channel needs room for at least one message");
+    let shared = Arc::new(Bounded {
+        capacity,
+        overflow,
+        counts,
+        state: Mutex::new(BoundedState {
+            queue: VecDeque::with_capacity(capacity),
+            senders: 1,
+            waker: None,
+        }),
+        available: Condvar::new(),
+        room: Condvar::new(),
+    });
+    (BoundedSender { shared: Arc::clone(&shared) }, BoundedReceiver { shared })
+}
+
+pub(crate) struct BoundedSender<T> {
+    shared: Arc<Bounded<T>>,
+}
+
+pub(crate) struct BoundedReceiver<T> {
+    shared: Arc<Bounded<T>>,
+}
+
+struct Bounded<T> {
+    capacity: usize,
+    overflow: Overflow,
+    counts: Arc<OverflowCounts>,
+    state: Mutex<BoundedState<T>>,
+    available: Condvar,
+    room: Condvar,
+}
+
+struct BoundedState<T> {
+    queue: VecDeque<T>,
+    senders: usize,
+    // the receiver's, waiting as a task
+    waker: Option<Waker>,
+}
+
+impl<T> Bounded<T> {
+    fn notify_receiver(&self, mut state: MutexGuard<BoundedState<T>>) {
+        let waker = state.waker.take();
+        drop(state);
+        self.available.notify_one();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    fn dropped(&self) {
+        self.counts.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl<T: Expendable> BoundedSender<T> {
+    pub(crate) fn send(&self, mut value: T) {
+        let shared = &*self.shared;
+        let mut state = shared.state.lock().unwrap();
+        if state.queue.len() >= shared.capacity {
+            match shared.overflow {
+                Overflow::Block => {
+                    while state.queue.len() >= shared.capacity {
+                        state = shared.room.wait(state).unwrap();
+                    }
+                }
+                _ if !value.is_expendable() => {}
+                Overflow::DropNewest => return shared.dropped(),
+                Overflow::DropOldest => match state.queue.iter().position(T::is_expendable) {
+                    Some(oldest) => {
+                        state.queue.remove(oldest);
+                        shared.dropped();
+                    }
+                    // the newest is the oldest one there is
+                    None => return shared.dropped(),
+                },
+                Overflow::Coalesce => {
+                    for waiting in state.queue.iter_mut().rev().filter(|waiting| waiting.is_expendable()) {
+                        match waiting.absorb(value) {
+                            Ok(()) => {
+                                shared.counts.coalesced.fetch_add(1, Ordering::Relaxed);
+                                return;
+                            }
+                            Err(rejected) => value = rejected,
+                        }
+                    }
+                    return shared.dropped();
+                }
+            }
+        }
+        state.queue.push_back(value);
+        shared.notify_receiver(state);
+    }
+}
+
+impl<T> Clone for BoundedSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().unwrap().senders += 1;
+        Self { shared: Arc::clone(&self.shared) }
+    }
+}
+
+impl<T> Drop for BoundedSender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.senders -= 1;
+        self.shared.notify_receiver(state);
+    }
+}
+
+impl<T> BoundedReceiver<T> {
+    // The next message, waiting for it if need be, or None once every sender is gone and every message is out
+    pub(crate) fn recv(&self) -> Option<T> {
+        let mut state = self.shared.state.lock().unwrap();
+        loop {
+            if let Some(value) = state.queue.pop_front() {
+                self.shared.room.notify_one();
+                return Some(value);
+            }
+            if state.senders == 0 {
+                return None;
+            }
+            state = self.shared.available.wait(state).unwrap();
+        }
+    }
+
+    // The same, for a task
+    pub(crate) async fn recv_async(&self) -> Option<T> {
+        std::future::poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    fn poll_recv(&self, cx: &mut Context) -> Poll<Option<T>> {
+        let mut state = self.shared.state.lock().unwrap();
+        if let Some(value) = state.queue.pop_front() {
+            self.shared.room.notify_one();
+            Poll::Ready(Some(value))
+        } else if state.senders == 0 {
+            Poll::Ready(None)
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+// A RingBuffer of at least the given capacity. Nothing ever blocks: senders finding the buffer full, and the
+// receiver finding it empty, keep trying, giving up their time slice in between.
+pub(crate) fn ring_channel<T>(capacity: usize) -> (RingSender<T>, RingReceiver<T>) {
+    let shared = Arc::new(Ring {
+        buffer: RingBuffer::with_capacity(capacity),
+        senders: AtomicUsize::new(1),
+    });
+    (RingSender { shared: Arc::clone(&shared) }, RingReceiver { shared })
+}
+
+pub(crate) struct RingSender<T> {
+    shared: Arc<Ring<T>>,
+}
+
+pub(crate) struct RingReceiver<T> {
+    shared: Arc<Ring<T>>,
+}
+
+struct Ring<T> {
+    buffer: RingBuffer<T>,
+    senders: AtomicUsize,
+}
+
+impl<T> RingSender<T> {
+    pub(crate) fn send(&self, mut value: T) {
+        while let Err(rejected) = self.shared.buffer.try_push(value) {
+            value = rejected;
+            thread::yield_now();
+        }
+    }
+}
+
+impl<T> Clone for RingSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::Relaxed);
+        Self { shared: Arc::clone(&self.shared) }
+    }
+}
+
+impl<T> Drop for RingSender<T> {
+    fn drop(&mut self) {
+        // everything this sender pushed is visible to whoever sees it gone
+        self.shared.senders.fetch_sub(1, Ordering::Release);
+    }
+}
+
+impl<T> RingReceiver<T> {
+    // The next value, spinning until there is one, or None once every sender is gone and every value is out
+    pub(crate) fn recv(&self) -> Option<T> {
+        loop {
+            if let Some(value) = self.shared.buffer.try_pop() {
+                return Some(value);
+            }
+            if self.shared.senders.load(Ordering::Acquire) == 0 {
+                return self.shared.buffer.try_pop();
+            }
+            thread::yield_now();
+        }
+    }
+}
+
+// A bounded queue that any number of threads can push to and pop from at the same time, without locks.
+//
+// Every slot has a sequence number telling whose turn it is: a pusher claims the slot at position pos once its
+// sequence is pos, and hands it over to poppers by setting it to pos + 1. A popper claims it then, and hands it
+// back to pushers, one lap later, by setting it to pos + capacity. Positions themselves are claimed by compare
+// and swap, so whoever loses the race just tries the next one.
+struct RingBuffer<T> {
+    slots: Box<[Slot<T>]>,
+    // capacity - 1, the capacity being a power of two
+    mask: usize,
+    // next position to push to, and to pop from
+    tail: AtomicUsize,
+    head: AtomicUsize,
+}
+
+struct Slot<T> {
+    sequence: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// SAFETY: a value is only ever accessed by the one thread that claimed its slot, see try_push and try_pop
+unsafe impl<T: Send> Sync for RingBuffer<T> {}
+
+impl<T> RingBuffer<T> {
+    fn with_capacity(capacity: usize) -> Self {
+        let capacity = capacity.max(2).next_power_of_two();
+        Self {
+            slots: (0..capacity).map(|pos| Slot {
+                sequence: AtomicUsize::new(pos),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+            }).collect(),
+            mask: capacity - 1,
+            tail: AtomicUsize::new(0),
+            head: AtomicUsize::new(0),
+        }
+    }
+
+    // Gives the value back if the buffer is full
+    fn try_push(&self, value: T) -> Result<(), T> {
+        let mut pos = self.tail.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos & self.mask];
+            let lag = slot.sequence.load(Ordering::Acquire).wrapping_sub(pos) as isize;
+            if lag == 0 {
+                match self.tail.compare_exchange_weak(pos, pos.wrapping_add(1), Ordering::Relaxed,
+                                                      Ordering::Relaxed) {
+                    Ok(_) => {
+                        // SAFETY: winning the compare and swap made the slot ours until we hand it over
+                        unsafe { (*slot.value.get()).write(value) };
+                        slot.sequence.store(pos.wrapping_add(1), Ordering::Release);
+                        return Ok(());
+                    }
+                    Err(current) => pos = current,
+                }
+            } else if lag < 0 {
+                // the slot still holds the value pushed a lap ago
+                return Err(value);
+            } else {
+                pos = self.tail.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn try_pop(&self) -> Option<T> {
+        let mut pos = self.head.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos & self.mask];
+            let lag = slot.sequence.load(Ordering::Acquire).wrapping_sub(pos.wrapping_add(1)) as isize;
+            if lag == 0 {
+                match self.head.compare_exchange_weak(pos, pos.wrapping_add(1), Ordering::Relaxed,
+                                                      Ordering::Relaxed) {
+                    Ok(_) => {
+                        // SAFETY: the slot was written before its sequence said so, and is ours until handed back
+                        let value = unsafe { (*slot.value.get()).assume_init_read() };
+                        slot.sequence.store(pos.wrapping_add(self.mask + 1), Ordering::Release);
+                        return Some(value);
+                    }
+                    Err(current) => pos = current,
+                }
+            } else if lag < 0 {
+                // nothing has been pushed there yet
+                return None;
+            } else {
+                pos = self.head.load(Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+impl<T> Drop for RingBuffer<T> {
+    fn drop(&mut self) {
+        while self.try_pop().is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Words from whoever said them, the loud ones never dropped nor merged
+    #[derive(Debug, PartialEq)]
+    struct Said(&'static str, String);
+
+    impl Expendable for Said {
+        fn is_expendable(&self) -> bool {
+            self.1 != self.1.to_uppercase()
+        }
+
+        fn absorb(&mut self, later: Self) -> Result<(), Self> {
+            if later.0 == self.0 {
+                self.1 = format!("{} {}", self.1, later.1);
+                Ok(())
+            } else {
+                Err(later)
+            }
+        }
+    }
+
+    // What is left in a channel of three after sending everything said, and how many were dropped and coalesced
+    fn overflow(overflow: Overflow, said: &[(&'static str, &str)]) -> (Vec<Said>, usize, usize) {
+        let counts = Arc::new(OverflowCounts::default());
+        let (sender, receiver) = bounded_channel(3, overflow, Arc::clone(&counts));
+        for (name, words) in said {
+            sender.send(Said(name, words.to_string()));
+        }
+        drop(sender);
+        (std::iter::from_fn(|| receiver.recv()).collect(), counts.dropped(), counts.coalesced())
+    }
+
+    fn said(said: &[(&'static str, &str)]) -> Vec<Said> {
+        said.iter().map(|(name, words)| Said(name, words.to_string())).collect()
+    }
+
+    #[test]
+    fn a_full_channel_drops_the_newest_or_oldest_messages_it_may_drop() {
+        let sent = [("Ana", "hi"), ("Bob", "HELLO"), ("Ana", "how"), ("Bob", "are"), ("Ana", "you"), ("Bob", "BYE")];
+        assert_eq!(overflow(Overflow::DropNewest, &sent),
+                   (said(&[("Ana", "hi"), ("Bob", "HELLO"), ("Ana", "how"), ("Bob", "BYE")]), 2, 0));
+        assert_eq!(overflow(Overflow::DropOldest, &sent),
+                   (said(&[("Bob", "HELLO"), ("Bob", "are"), ("Ana", "you"), ("Bob", "BYE")]), 2, 0));
+    }
+
+    #[test]
+    fn a_full_channel_can_merge_messages_from_the_same_sender() {
+        let sent = [("Ana", "hi"), ("Bob", "hello"), ("Cid", "HEY"), ("Ana", "there"), ("Bob", "you"), ("Dan", "yo")];
+        assert_eq!(overflow(Overflow::Coalesce, &sent),
+                   (said(&[("Ana", "hi there"), ("Bob", "hello you"), ("Cid", "HEY")]), 1, 2));
+    }
+
+    #[test]
+    fn a_full_channel_can_make_senders_wait_for_room() {
+        let counts = Arc::new(OverflowCounts::default());
+        let (sender, receiver) = bounded_channel(1, Overflow::Block, Arc::clone(&counts));
+        let handle = thread::spawn(move || (0..100).for_each(|n| sender.send(Said("Ana", n.to_string()))));
+        let received: Vec<_> = std::iter::from_fn(|| receiver.recv()).map(|Said(_, words)| words).collect();
+        handle.join().unwrap();
+        assert_eq!(received, (0..100).map(|n| n.to_string()).collect::<Vec<_>>());
+        assert_eq!((counts.dropped(), counts.coalesced()), (0, 0));
+    }
+
+    #[test]
+    fn the_ring_buffer_is_first_in_first_out_and_bounded() {
+        let ring = RingBuffer::with_capacity(3);
+        for value in 0..4 {
+            assert_eq!(ring.try_push(value), Ok(()));
+        }
+        assert_eq!(ring.try_push(4), Err(4));
+        assert_eq!(ring.try_pop(), Some(0));
+        assert_eq!(ring.try_push(4), Ok(()));
+        assert_eq!((0..5).map(|_| ring.try_pop()).collect::<Vec<_>>(), vec![Some(1), Some(2), Some(3), Some(4), None]);
+    }
+
+    // What four senders sent, 0 to 999 each, tagged with who they are, has all arrived, each sender's in order
+    fn assert_received_everything(received: Vec<(usize, usize)>) {
+        assert_eq!(received.len(), 4000);
+        for id in 0..4 {
+            let values: Vec<_> = received.iter().filter(|(sender, _)| *sender == id).map(|&(_, value)| value).collect();
+            assert_eq!(values, (0..1000).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn every_value_goes_through_the_locked_queue() {
+        let (sender, receiver) = locked_channel();
+        for id in 0..4 {
+            let sender = sender.clone();
+            thread::spawn(move || (0..1000).for_each(|value| sender.send((id, value))));
+        }
+        drop(sender);
+        assert_received_everything(std::iter::from_fn(|| receiver.recv()).collect());
+    }
+
+    #[test]
+    fn every_value_goes_through_the_ring_buffer_however_small() {
+        let (sender, receiver) = ring_channel(2);
+        for id in 0..4 {
+            let sender = sender.clone();
+            thread::spawn(move || (0..1000).for_each(|value| sender.send((id, value))));
+        }
+        drop(sender);
+        assert_received_everything(std::iter::from_fn(|| receiver.recv()).collect());
+    }
+}
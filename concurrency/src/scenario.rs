@@ -0,0 +1,394 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::queues::Overflow;
+
+const DEFAULT_SCENARIO: &str = include_str!("../scenarios/default.toml");
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PresenterConfig {
+    pub(crate) name: String,
+    pub(crate) pace_ms: u64,
+    // time spent answering every question
+    #[serde(default = "default_answer_ms")]
+    pub(crate) answer_ms: u64,
+    // whether to wait for every student to react to a concept before going on to the next one
+    #[serde(default)]
+    pub(crate) wait_for_reactions: bool,
+    // whether, and when, to send the class to breakout rooms, see breakout.rs
+    pub(crate) breakout: Option<BreakoutConfig>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct BreakoutConfig {
+    // how many concepts are presented before the class breaks out
+    pub(crate) after_concept: usize,
+    pub(crate) rooms: usize,
+    // how long the rooms are open for
+    pub(crate) duration_ms: u64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Concept {
+    pub(crate) title: String,
+    #[serde(default)]
+    pub(crate) tricky: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct StudentProfile {
+    pub(crate) name: String,
+    pub(crate) chattiness: f64,
+    pub(crate) confusion: f64,
+    // how likely the student is to react to somebody else's chat message
+    #[serde(default)]
+    pub(crate) reactiveness: f64,
+    // how likely the student is to send somebody a private message about a concept they followed
+    #[serde(default)]
+    pub(crate) whispering: f64,
+    pub(crate) pace_ms: u64,
+}
+
+// How many events may wait for the chat screen, and what happens to chat messages that do not fit
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ChatConfig {
+    // without limit if not given
+    pub(crate) capacity: Option<usize>,
+    #[serde(default)]
+    pub(crate) overflow: Overflow,
+    #[serde(default)]
+    pub(crate) filters: FilterConfig,
+}
+
+// What the chat screen filters messages through, see moderation.rs. Every filter is left out unless set.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FilterConfig {
+    pub(crate) rate_limit: Option<RateLimitConfig>,
+    // how long a message is held back for if its sender already sent it
+    pub(crate) duplicate_window_ms: Option<u64>,
+    #[serde(default)]
+    pub(crate) banned_words: Vec<String>,
+    // in characters
+    pub(crate) max_length: Option<usize>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct RateLimitConfig {
+    // messages every participant may send per second, once done with the burst
+    pub(crate) per_second: f64,
+    pub(crate) burst: u32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Scenario {
+    // nobody may keep the floor for longer than this while others are waiting to speak
+    #[serde(default = "default_max_speaking_ms")]
+    pub(crate) max_speaking_ms: u64,
+    pub(crate) presenter: PresenterConfig,
+    pub(crate) concepts: Vec<Concept>,
+    pub(crate) students: Vec<StudentProfile>,
+    #[serde(default)]
+    pub(crate) chat: ChatConfig,
+    // makes every student's choices the same from one run to the next, see Student::with_seed
+    pub(crate) seed: Option<u64>,
+}
+
+#[derive(Debug)]
+pub(crate) enum ScenarioError {
+    Io(String, io::Error),
+    Parse(toml::de::Error),
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScenarioError::Io(path, e) => write!(f, "could not read scenario file {}: {}", path, e),
+            ScenarioError::Parse(e) => write!(f, "could not parse scenario: {}", e),
+            ScenarioError::Invalid(problems) => {
+                write!(f, "invalid scenario:")?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn default_answer_ms() -> u64 {
+    500
+}
+
+fn default_max_speaking_ms() -> u64 {
+    5000
+}
+
+impl PresenterConfig {
+    pub(crate) fn pace(&self) -> Duration {
+        Duration::from_millis(self.pace_ms)
+    }
+
+    pub(crate) fn answer_time(&self) -> Duration {
+        Duration::from_millis(self.answer_ms)
+    }
+}
+
+impl BreakoutConfig {
+    pub(crate) fn duration(&self) -> Duration {
+        Duration::from_millis(self.duration_ms)
+    }
+}
+
+impl StudentProfile {
+    pub(crate) fn pace(&self) -> Duration {
+        Duration::from_millis(self.pace_ms)
+    }
+}
+
+impl Scenario {
+    pub(crate) fn max_speaking_time(&self) -> Duration {
+        Duration::from_millis(self.max_speaking_ms)
+    }
+
+    // How many breakout rooms the presenter opens, if any: never more than students, see Breakout::open
+    pub(crate) fn breakout_rooms(&self) -> usize {
+        self.presenter.breakout.as_ref().map_or(0, |breakout| breakout.rooms.min(self.students.len()))
+    }
+
+    // As many students as given, going through the scenario's own students over and over again for a large class.
+    // From the second round on, names get the round number.
+    pub(crate) fn with_class_size(mut self, students: usize) -> Self {
+        let profiles = std::mem::take(&mut self.students);
+        self.students = profiles.iter().cycle().take(students).enumerate().map(|(idx, profile)| {
+            let round = idx / profiles.len();
+            let mut profile = profile.clone();
+            if round > 0 {
+                profile.name = format!("{} ({})", profile.name, round + 1);
+            }
+            profile
+        }).collect();
+        self
+    }
+
+    pub(crate) fn load(path: &str) -> Result<Self, ScenarioError> {
+        let contents = fs::read_to_string(path).map_err(|e| ScenarioError::Io(path.to_string(), e))?;
+        Scenario::parse(&contents)
+    }
+
+    pub(crate) fn parse(contents: &str) -> Result<Self, ScenarioError> {
+        let scenario: Scenario = toml::from_str(contents).map_err(ScenarioError::Parse)?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    // Reports every problem found, not just the first one
+    fn validate(&self) -> Result<(), ScenarioError> {
+        let mut problems = vec![];
+        if self.presenter.name.trim().is_empty() {
+            problems.push("the presenter needs a name".to_string());
+        }
+        if self.concepts.is_empty() {
+            problems.push("there has to be at least one concept".to_string());
+        }
+        for (idx, concept) in self.concepts.iter().enumerate() {
+            if concept.title.trim().is_empty() {
+                problems.push(format!("concept #{} needs a title", idx + 1));
+            }
+        }
+        if self.students.is_empty() {
+            problems.push("there has to be at least one student".to_string());
+        }
+        if let Some(breakout) = &self.presenter.breakout {
+            if !(1..=self.concepts.len()).contains(&breakout.after_concept) {
+                problems.push(format!("the class can only break out after one of the {} concepts, not after {}",
+                                      self.concepts.len(), breakout.after_concept));
+            }
+            if breakout.rooms == 0 || breakout.duration_ms == 0 {
+                problems.push("breakout rooms need at least one room, open for some time".to_string());
+            }
+        }
+        let mut names = HashSet::new();
+        names.insert(self.presenter.name.as_str());
+        for (idx, student) in self.students.iter().enumerate() {
+            if student.name.trim().is_empty() {
+                problems.push(format!("student #{} needs a name", idx + 1));
+            } else if !names.insert(student.name.as_str()) {
+                problems.push(format!("the name {} is used more than once", student.name));
+            }
+            let probabilities = [("chattiness", student.chattiness), ("confusion", student.confusion),
+                                 ("reactiveness", student.reactiveness), ("whispering", student.whispering)];
+            for (field, value) in probabilities.iter() {
+                if !(0.0..=1.0).contains(value) {
+                    problems.push(format!("{}'s {} has to be between 0.0 and 1.0, not {}", student.name, field, value));
+                }
+            }
+        }
+        if self.chat.capacity == Some(0) {
+            problems.push("the chat needs room for at least one message".to_string());
+        }
+        let filters = &self.chat.filters;
+        if let Some(rate_limit) = &filters.rate_limit {
+            if rate_limit.per_second.is_nan() || rate_limit.per_second <= 0.0 || rate_limit.burst == 0 {
+                problems.push("the chat rate limit needs a positive rate, and a burst of at least one message"
+                    .to_string());
+            }
+        }
+        if filters.banned_words.iter().any(|word| !word.chars().all(char::is_alphanumeric) || word.is_empty()) {
+            problems.push("banned words have to be single words".to_string());
+        }
+        if filters.max_length == Some(0) {
+            problems.push("the chat needs messages of at least one character".to_string());
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ScenarioError::Invalid(problems))
+        }
+    }
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Scenario::parse(DEFAULT_SCENARIO).expect("The default scenario is invalid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINIMAL: &str = r#"
+[presenter]
+name = "Mat"
+pace_ms = 1000
+
+[[concepts]]
+title = "Traits"
+
+[[students]]
+name = "Ana"
+chattiness = 0.5
+confusion = 0.5
+pace_ms = 1000
+"#;
+
+    // Every problem found with the minimal scenario, changed as given
+    fn problems(from: &str, to: &str) -> Vec<String> {
+        assert!(MINIMAL.contains(from), "nothing to replace");
+        match Scenario::parse(&MINIMAL.replacen(from, to, 1)) {
+            Err(ScenarioError::Invalid(problems)) => problems,
+            Err(e) => panic!("{}", e),
+            Ok(_) => vec![],
+        }
+    }
+
+    fn with(extra: &str) -> Vec<String> {
+        problems("pace_ms = 1000\n\n[[concepts]]", &format!("pace_ms = 1000\n{}\n\n[[concepts]]", extra))
+    }
+
+    #[test]
+    fn every_bundled_scenario_is_valid() {
+        let default = Scenario::default();
+        assert_eq!((default.concepts.len(), default.students.len()), (6, 3));
+        assert!(default.presenter.breakout.is_none() && default.chat.capacity.is_none());
+
+        let mixed = Scenario::parse(include_str!("../scenarios/mixed_class.toml")).unwrap();
+        assert_eq!((mixed.concepts.len(), mixed.students.len()), (5, 4));
+        assert!(mixed.presenter.wait_for_reactions && default.seed.is_none());
+        assert_eq!(mixed.seed, Some(2020));
+        assert_eq!(mixed.presenter.breakout.map(|breakout| (breakout.after_concept, breakout.rooms)), Some((3, 2)));
+        assert_eq!(mixed.chat.filters.max_length, Some(24));
+        assert_eq!(problems("", ""), Vec::<String>::new());
+    }
+
+    #[test]
+    fn misspelled_keys_are_rejected_wherever_they_are() {
+        for (from, to) in [("pace_ms = 1000\n\n[[concepts]]", "pace_ms = 1000\nwait_for_reaction = true\n[[concepts]]"),
+                           ("title = \"Traits\"", "title = \"Traits\"\ntricky_ = true"),
+                           ("confusion = 0.5", "confusion = 0.5\nchatiness = 0.5")].iter() {
+            match Scenario::parse(&MINIMAL.replacen(from, to, 1)) {
+                Err(ScenarioError::Parse(e)) => assert!(e.to_string().contains("unknown field"), "{}", e),
+                _ => panic!("{} was accepted", to),
+            }
+        }
+        let chat = format!("{}\n[chat]\ncapacity = 4\n[chat.filters]\nmax_lenght = 3\n", MINIMAL);
+        assert!(matches!(Scenario::parse(&chat), Err(ScenarioError::Parse(_))));
+        let top_level = format!("max_speaking_time = 10\n{}", MINIMAL);
+        assert!(matches!(Scenario::parse(&top_level), Err(ScenarioError::Parse(_))));
+    }
+
+    #[test]
+    fn everybody_needs_a_name_of_their_own() {
+        assert_eq!(problems("name = \"Mat\"", "name = \" \""), vec!["the presenter needs a name"]);
+        assert_eq!(problems("name = \"Ana\"", "name = \"\""), vec!["student #1 needs a name"]);
+        assert_eq!(problems("name = \"Ana\"", "name = \"Mat\""), vec!["the name Mat is used more than once"]);
+        assert_eq!(problems("title = \"Traits\"", "title = \"\""), vec!["concept #1 needs a title"]);
+    }
+
+    #[test]
+    fn a_session_needs_concepts_and_a_class() {
+        let empty = "concepts = []\nstudents = []\n[presenter]\nname = \"Mat\"\npace_ms = 1000\n";
+        match Scenario::parse(empty) {
+            Err(ScenarioError::Invalid(problems)) => assert_eq!(problems, vec![
+                "there has to be at least one concept",
+                "there has to be at least one student",
+            ]),
+            _ => panic!("an empty session was accepted"),
+        }
+    }
+
+    #[test]
+    fn probabilities_have_to_be_between_zero_and_one() {
+        assert_eq!(problems("chattiness = 0.5", "chattiness = 1.5"),
+                   vec!["Ana's chattiness has to be between 0.0 and 1.0, not 1.5"]);
+        assert_eq!(problems("confusion = 0.5", "confusion = 0.5\nreactiveness = -0.1\nwhispering = nan"), vec![
+            "Ana's reactiveness has to be between 0.0 and 1.0, not -0.1",
+            "Ana's whispering has to be between 0.0 and 1.0, not NaN",
+        ]);
+    }
+
+    #[test]
+    fn the_chat_and_its_filters_need_sensible_limits() {
+        let invalid = |section: &str| match Scenario::parse(&format!("{}\n{}", MINIMAL, section)) {
+            Err(ScenarioError::Invalid(problems)) => problems,
+            other => panic!("{} gave {:?}", section, other.map(|_| ())),
+        };
+        assert_eq!(invalid("[chat]\ncapacity = 0"), vec!["the chat needs room for at least one message"]);
+        assert_eq!(invalid("[chat.filters]\nrate_limit = { per_second = 0.0, burst = 1 }"),
+                   vec!["the chat rate limit needs a positive rate, and a burst of at least one message"]);
+        assert_eq!(invalid("[chat.filters]\nrate_limit = { per_second = 1.0, burst = 0 }"),
+                   vec!["the chat rate limit needs a positive rate, and a burst of at least one message"]);
+        assert_eq!(invalid("[chat.filters]\nbanned_words = [\"two words\"]"),
+                   vec!["banned words have to be single words"]);
+        assert_eq!(invalid("[chat.filters]\nbanned_words = [\"\"]"), vec!["banned words have to be single words"]);
+        assert_eq!(invalid("[chat.filters]\nmax_length = 0"),
+                   vec!["the chat needs messages of at least one character"]);
+    }
+
+    #[test]
+    fn the_class_can_only_break_out_after_a_concept_and_for_a_while() {
+        let breakout = |after: usize, rooms: usize, duration_ms: u64| {
+            with(&format!("breakout = {{ after_concept = {}, rooms = {}, duration_ms = {} }}", after, rooms,
+                          duration_ms))
+        };
+        assert_eq!(breakout(1, 2, 1000), Vec::<String>::new());
+        assert_eq!(breakout(0, 2, 1000), vec!["the class can only break out after one of the 1 concepts, not after 0"]);
+        assert_eq!(breakout(2, 2, 1000), vec!["the class can only break out after one of the 1 concepts, not after 2"]);
+        assert_eq!(breakout(1, 0, 1000), vec!["breakout rooms need at least one room, open for some time"]);
+        assert_eq!(breakout(1, 1, 0), vec!["breakout rooms need at least one room, open for some time"]);
+    }
+}
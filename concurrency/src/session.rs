@@ -0,0 +1,600 @@
+use std::fmt;
+use std::fmt::Write;
+use std::future::Future;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+use crate::board::ConceptBoard;
+use crate::breakout::Breakout;
+use crate::broadcast;
+use crate::broadcast::Subscriber;
+use crate::bus;
+use crate::bus::EventSender;
+use crate::chat::{ChatMessage, ChatScreen};
+use crate::clock::Clock;
+use crate::direct::{Router, RoutingCounts};
+use crate::events::Event;
+use crate::executor::Executor;
+use crate::floor::{Floor, FloorTurn};
+use crate::metrics::{ChatLatencies, OverflowMetrics, SessionMetrics, SpeakWaits};
+use crate::moderation::ChatPipeline;
+use crate::participants;
+use crate::participants::{Presenter, Stage, Student, Utterance};
+use crate::questions::Answer;
+use crate::queues::OverflowCounts;
+use crate::scenario::Scenario;
+use crate::tasks;
+
+// How many events a student can fall behind on before missing some
+const FEED_CAPACITY: usize = 64;
+
+// Everything that happened during a session, in order
+#[derive(Debug)]
+pub(crate) struct Transcript {
+    pub(crate) events: Vec<Event>,
+}
+
+// What a session leaves behind
+pub(crate) struct SessionRecord {
+    pub(crate) transcript: Transcript,
+    pub(crate) metrics: SessionMetrics,
+    pub(crate) floor_log: Vec<FloorTurn>,
+    // private messages are only counted
+    pub(crate) direct_messages: RoutingCounts,
+}
+
+impl Transcript {
+    // From the first event to the last one
+    pub(crate) fn duration(&self) -> chrono::Duration {
+        match (self.events.first(), self.events.last()) {
+            (Some(first), Some(last)) => last.timestamp() - first.timestamp(),
+            _ => chrono::Duration::zero(),
+        }
+    }
+
+    pub(crate) fn spoken(&self) -> Vec<&Utterance> {
+        self.events.iter().filter_map(|event| match event {
+            Event::Spoke(utterance) => Some(utterance),
+            _ => None,
+        }).collect()
+    }
+
+    // Messages and reactions alike
+    pub(crate) fn chat(&self) -> Vec<&ChatMessage> {
+        self.events.iter().filter_map(|event| match event {
+            Event::ChatMessage(msg) | Event::Reaction(msg) => Some(msg),
+            _ => None,
+        }).collect()
+    }
+
+    pub(crate) fn answers(&self) -> Vec<&Answer> {
+        self.events.iter().filter_map(|event| match event {
+            Event::Answered(answer) => Some(answer),
+            _ => None,
+        }).collect()
+    }
+}
+
+impl SessionRecord {
+    // What is printed once the session is over, given the files it was saved to: who asked what, private messages,
+    // the metrics report and who held the floor, and last of all how long the session took
+    pub(crate) fn summary(&self, written: &[&str]) -> String {
+        let tid = thread::current().id();
+        let transcript = &self.transcript;
+        let mut summary = String::new();
+        // writing to a String never fails
+        writeln!(summary).unwrap();
+        for answer in transcript.answers() {
+            writeln!(summary, "{:?}: {} asked about {} and {} answered after {}ms", tid, answer.question.student,
+                     answer.question.concept, answer.presenter, answer.waited().num_milliseconds()).unwrap();
+        }
+        writeln!(summary).unwrap();
+        writeln!(summary, "{:?}: {} private messages were delivered, {} went nowhere", tid,
+                 self.direct_messages.delivered, self.direct_messages.undeliverable).unwrap();
+        writeln!(summary).unwrap();
+        writeln!(summary, "{}", self.metrics.report()).unwrap();
+
+        writeln!(summary).unwrap();
+        writeln!(summary, "{:?}: Who held the floor:", tid).unwrap();
+        for turn in &self.floor_log {
+            writeln!(summary, "{:?}: {} - {} {}{}", tid, turn.from.format("%H:%M:%S%.3f"),
+                     turn.until.format("%H:%M:%S%.3f"), turn.name, if turn.cut_off { " (cut off)" } else { "" })
+                .unwrap();
+        }
+        for path in written {
+            writeln!(summary, "{:?}: Wrote {}", tid, path).unwrap();
+        }
+
+        writeln!(summary).unwrap();
+        write!(summary, "{:?}: End of training session after {}ms, {} things were said out loud and {} chat messages \
+                         were sent", tid, transcript.duration().num_milliseconds(), transcript.spoken().len(),
+               transcript.chat().len()).unwrap();
+        summary
+    }
+}
+
+// How participants are run: with threads, the presenter and the event bus always have one each. Either way the
+// router has one of its own.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Execution {
+    ThreadPerStudent,
+    // as cooperative tasks on a pool with this many workers, see tasks.rs
+    Pool { workers: usize },
+    // everybody, the event bus included, as tasks on an executor with this many threads, see executor.rs
+    Async { threads: usize },
+}
+
+impl Execution {
+    // How many threads the whole session runs on at most, besides the one that started it, every breakout room
+    // having a screen on a thread of its own while open
+    pub(crate) fn threads(&self, students: usize, rooms: usize) -> usize {
+        rooms + match self {
+            Execution::ThreadPerStudent => students + 3,
+            // the workers, and the scheduler handing them students
+            Execution::Pool { workers } => workers + 4,
+            // the thread that started it works too, but not for the router
+            Execution::Async { threads } => *threads,
+        }
+    }
+}
+
+impl fmt::Display for Execution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Execution::ThreadPerStudent => write!(f, "a thread per student"),
+            Execution::Pool { workers: 1 } => write!(f, "a pool of 1 worker"),
+            Execution::Pool { workers } => write!(f, "a pool of {} workers", workers),
+            Execution::Async { threads: 1 } => write!(f, "async on 1 thread"),
+            Execution::Async { threads } => write!(f, "async on {} threads", threads),
+        }
+    }
+}
+
+// Runs the whole training session, returning once every participant has left and every event has been handled
+pub(crate) fn run_session(scenario: Scenario, clock: Arc<dyn Clock>, execution: Execution) -> SessionRecord {
+    let floor = Arc::new(Floor::new(&scenario.presenter.name, scenario.max_speaking_time()));
+    let speak_waits = Arc::new(SpeakWaits::default());
+    let chat = scenario.chat.clone();
+    // only ever counted with a bounded chat
+    let overflow_counts = Arc::new(OverflowCounts::default());
+    let chat_latencies = Arc::new(ChatLatencies::default());
+    // every student follows what the chat screen shows, starting before anything can happen
+    let feed = broadcast::channel();
+    let feeds = scenario.students.iter().map(|_| feed.subscribe(FEED_CAPACITY)).collect();
+    let chat_screen = Box::new(ChatScreen::new(ChatPipeline::new(&chat.filters), Arc::clone(&clock),
+                                               Arc::clone(&chat_latencies), feed));
+    let (events, direct_messages) = match (execution, chat.capacity) {
+        (Execution::Async { threads }, Some(capacity)) => {
+            let counts = Arc::clone(&overflow_counts);
+            let (events_tx, mut hub) = bus::bounded_async_event_bus(capacity, chat.overflow, counts);
+            hub.add_consumer(chat_screen);
+            let cast = Cast::new(scenario, clock, events_tx, feeds, Arc::clone(&floor), Arc::clone(&speak_waits));
+            run_on_executor(cast, hub.run_async(), threads)
+        }
+        (Execution::Async { threads }, None) => {
+            let (events_tx, mut hub) = bus::async_event_bus();
+            hub.add_consumer(chat_screen);
+            let cast = Cast::new(scenario, clock, events_tx, feeds, Arc::clone(&floor), Arc::clone(&speak_waits));
+            run_on_executor(cast, hub.run_async(), threads)
+        }
+        (_, Some(capacity)) => {
+            let (events_tx, mut hub) = bus::bounded_event_bus(capacity, chat.overflow, Arc::clone(&overflow_counts));
+            hub.add_consumer(chat_screen);
+            let cast = Cast::new(scenario, clock, events_tx, feeds, Arc::clone(&floor), Arc::clone(&speak_waits));
+            run_on_threads(cast, move || hub.run(), execution)
+        }
+        (_, None) => {
+            let (events_tx, mut hub) = bus::event_bus();
+            hub.add_consumer(chat_screen);
+            let cast = Cast::new(scenario, clock, events_tx, feeds, Arc::clone(&floor), Arc::clone(&speak_waits));
+            run_on_threads(cast, move || hub.run(), execution)
+        }
+    };
+
+    let transcript = Transcript {
+        events,
+    };
+    let chat_overflow = chat.capacity.map(|capacity| OverflowMetrics {
+        capacity,
+        policy: chat.overflow,
+        dropped: overflow_counts.dropped(),
+        coalesced: overflow_counts.coalesced(),
+    });
+    let metrics = SessionMetrics::measure(&transcript, &speak_waits, &chat_latencies, chat_overflow);
+    SessionRecord {
+        transcript,
+        metrics,
+        floor_log: floor.log(),
+        direct_messages,
+    }
+}
+
+// Everybody taking part in the session, on stage and ready to go, and the router for their private messages
+struct Cast {
+    stage: Arc<Stage>,
+    presenter: Presenter,
+    students: Vec<Student>,
+    router: Router,
+}
+
+impl Cast {
+    fn new(scenario: Scenario, clock: Arc<dyn Clock>, events_tx: EventSender, feeds: Vec<Subscriber<Event>>,
+           floor: Arc<Floor>, speak_waits: Arc<SpeakWaits>) -> Self {
+        let concepts = Arc::new(scenario.concepts);
+        let board = ConceptBoard::new(Arc::clone(&clock), concepts.len(), scenario.students.len());
+        // every student goes to a breakout room, if the presenter ever opens any
+        let class = scenario.students.iter().map(|profile| profile.name.clone()).collect();
+        let breakout = Breakout::new(scenario.presenter.breakout.clone(), class, scenario.chat.filters.clone());
+        let feeds: Vec<_> = feeds.into_iter().map(|feed| events_tx.follow(feed)).collect();
+        let stage = Arc::new(Stage::new(clock, events_tx, board, floor, speak_waits, breakout));
+
+        // Students ask the presenter over a channel of their own. The presenter takes questions until every
+        // student has left, dropping their senders.
+        let (questions_tx, questions_rx) = mpsc::channel();
+        // everybody gets a mailbox, and can send private messages to anybody else
+        let mut router = Router::new(Arc::clone(&stage.clock));
+        let names: Vec<String> = Some(&scenario.presenter.name).into_iter()
+            .chain(scenario.students.iter().map(|profile| &profile.name))
+            .cloned()
+            .collect();
+        // every student gets a seed of their own, if the scenario has one
+        let seed = scenario.seed;
+        let students = scenario.students.into_iter().zip(feeds).enumerate().map(|(idx, (profile, feed))| {
+            let questions_tx = mpsc::Sender::clone(&questions_tx);
+            let mailbox = router.register(&profile.name);
+            let contacts = names.iter().filter(|name| **name != profile.name).cloned().collect();
+            let student = Student::new(profile, Arc::clone(&concepts), feed, questions_tx, mailbox, contacts,
+                                       Arc::clone(&stage));
+            match seed {
+                Some(seed) => student.with_seed(seed ^ idx as u64),
+                None => student,
+            }
+        }).collect();
+        let mailbox = router.register(&scenario.presenter.name);
+        let presenter = Presenter::new(scenario.presenter, concepts, questions_rx, mailbox, Arc::clone(&stage));
+        Self {
+            stage,
+            presenter,
+            students,
+            router,
+        }
+    }
+}
+
+// The presenter, the event bus and the router on a thread each, and students on a thread each or on a pool
+fn run_on_threads(cast: Cast, hub: impl FnOnce() -> Vec<Event> + Send + 'static,
+                  execution: Execution) -> (Vec<Event>, RoutingCounts) {
+    let Cast { stage, presenter, students, router } = cast;
+    let mut thread_handles = vec![];
+    let hub_handle = thread::spawn(hub);
+
+    // every participant, and the router, must be known to the clock before any of them starts, a pool being a
+    // single one
+    let presenter_actor = stage.clock.register_actor();
+    let student_actors: Vec<_> = match execution {
+        Execution::Pool { .. } => vec![stage.clock.register_actor()],
+        _ => students.iter().map(|_| stage.clock.register_actor()).collect(),
+    };
+    let router_actor = stage.clock.register_actor();
+    let router_handle = thread::spawn(move || router.run(router_actor));
+
+    thread_handles.push(thread::spawn(move || participants::attend(presenter, presenter_actor)));
+    match execution {
+        Execution::Pool { workers } => {
+            let pool_stage = Arc::clone(&stage);
+            let actor = student_actors[0];
+            thread_handles.push(thread::spawn(move || tasks::run_on_pool(students, workers, pool_stage, actor)));
+        }
+        _ => {
+            for (student, actor) in students.into_iter().zip(student_actors) {
+                // A move closure transfers ownership of values from one thread to another.
+                // Needed to be able to use variable student (created in main thread) in each spawned thread.
+                thread_handles.push(thread::spawn(move || participants::attend(student, actor)));
+            }
+        }
+    }
+
+    // Every participant owns a clone of the stage, so once they are all done the only one left is the original.
+    // Dropping it lets the event bus know that no more events will ever arrive.
+    drop(stage);
+
+    // block until all participants have finished
+    for handle in thread_handles {
+        handle.join().unwrap();
+    }
+
+    // and then until every event and private message sent before they left has been handled
+    (hub_handle.join().unwrap(), router_handle.join().unwrap())
+}
+
+// Everybody, the event bus included, as tasks on an executor. The router still has a thread of its own.
+fn run_on_executor(cast: Cast, hub: impl Future<Output = Vec<Event>> + Send + 'static,
+                   threads: usize) -> (Vec<Event>, RoutingCounts) {
+    let Cast { stage, presenter, students, router } = cast;
+    let executor = Executor::new(Arc::clone(&stage.clock));
+    let router_actor = stage.clock.register_actor();
+    let router_handle = thread::spawn(move || router.run(router_actor));
+    executor.spawn(participants::attend_async(presenter));
+    for student in students {
+        executor.spawn(participants::attend_async(student));
+    }
+    let (events_tx, events_rx) = mpsc::channel();
+    executor.spawn(async move { events_tx.send(hub.await).unwrap() });
+
+    // as with threads, the event bus closes once every participant is done
+    drop(stage);
+    executor.run(threads);
+    (events_rx.recv().unwrap(), router_handle.join().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clock::SimulatedClock;
+    use crate::queues::Overflow;
+    use crate::scenario::{BreakoutConfig, ChatConfig};
+
+    use super::*;
+
+    // (name, text, milliseconds since the start of the session)
+    fn entries<'a>(items: impl Iterator<Item = (&'a str, &'a str, i64)>) -> Vec<(String, String, i64)> {
+        items.map(|(name, text, ms)| (String::from(name), String::from(text), ms)).collect()
+    }
+
+    fn expected(items: &[(&str, &str, i64)]) -> Vec<(String, String, i64)> {
+        entries(items.iter().copied())
+    }
+
+    #[test]
+    fn default_session_transcript_is_deterministic() {
+        let clock = Arc::new(SimulatedClock::starting_at_epoch());
+        let start = clock.now();
+        let record = run_session(Scenario::default(), clock, Execution::ThreadPerStudent);
+        let transcript = record.transcript;
+
+        // everybody comes and goes, and the events are in order
+        let joined = transcript.events.iter().filter(|e| matches!(e, Event::Joined { .. })).count();
+        let left = transcript.events.iter().filter(|e| matches!(e, Event::Left { .. })).count();
+        assert_eq!((joined, left), (4, 4));
+        assert!(transcript.events.windows(2).all(|pair| pair[0].timestamp() <= pair[1].timestamp()));
+        // the presenter leaves last, once done answering questions (see below)
+        assert_eq!(transcript.duration().num_milliseconds(), 12400 + 500);
+
+        let spoken = entries(transcript.spoken().into_iter()
+            .map(|u| (u.name.as_str(), u.text.as_str(), (u.timestamp - start).num_milliseconds())));
+        assert_eq!(spoken, expected(&[
+            ("Mat", "Let me tell you about Amazing thing #1...", 0),
+            ("Mat", "Let me tell you about Amazing thing #2...", 1500),
+            ("Mat", "Let me tell you about Amazing thing #3...", 3000),
+            ("Mat", "Let me tell you about Amazing thing #4...", 4500),
+            // students take a while to take every concept in, one after the other (concept #3 from 3400 to 5100),
+            // and then nobody interrupts the presenter, so they wait for the end of concept #4 to speak up.
+            ("Student_1", "Amazing thing #3 sounds tricky :-(", 6000),
+            // The presenter raised a hand for the next concept in the meantime, and goes first.
+            ("Mat", "Let me tell you about Amazing thing #5...", 6100),
+            // Student_2 and Student_3 gave up waiting at 6800, and just asked
+            ("Mat", "Student_1 asked about Amazing thing #3, let me explain it again...", 7600),
+            ("Mat", "Student_2 asked about Amazing thing #3, let me explain it again...", 8100),
+            ("Mat", "Student_3 asked about Amazing thing #3, let me explain it again...", 8600),
+            ("Mat", "Let me tell you about Amazing thing #6...", 9100),
+            // students take turns in the order they raised their hands, in between the presenter's answers
+            ("Student_1", "Amazing thing #6 sounds tricky :-(", 11100),
+            ("Mat", "Student_1 asked about Amazing thing #6, let me explain it again...", 11100),
+            ("Student_2", "Amazing thing #6 sounds tricky :-(", 11900),
+            ("Student_3", "Amazing thing #6 sounds tricky :-(", 11900),
+            ("Mat", "Student_2 asked about Amazing thing #6, let me explain it again...", 11900),
+            ("Mat", "Student_3 asked about Amazing thing #6, let me explain it again...", 12400),
+        ]));
+
+        let waited: Vec<(&str, &str, i64)> = transcript.answers().into_iter()
+            .map(|a| (a.question.student.as_str(), a.question.concept.as_str(), a.waited().num_milliseconds()))
+            .collect();
+        assert_eq!(waited, vec![
+            ("Student_1", "Amazing thing #3", 1600),
+            ("Student_2", "Amazing thing #3", 1300),
+            ("Student_3", "Amazing thing #3", 1800),
+            ("Student_1", "Amazing thing #6", 0),
+            ("Student_2", "Amazing thing #6", 0),
+            ("Student_3", "Amazing thing #6", 500),
+        ]);
+
+        // chat needs no floor, but students who waited to speak are late for the next concepts
+        let chat = entries(transcript.chat().into_iter()
+            .map(|m| (m.username.as_str(), m.text.as_str(), (m.timestamp - start).num_milliseconds())));
+        let mut expected_chat = vec![];
+        for (concept, times) in [(1, [1700, 1700, 1700]), (2, [3400, 3400, 3400]), (4, [7700, 8500, 8500]),
+                                 (5, [9400, 10200, 10200])].iter() {
+            for (student, ms) in times.iter().enumerate() {
+                expected_chat.push((format!("Student_{}", student + 1), format!("Amazing thing #{} is cool!", concept),
+                                    *ms));
+            }
+        }
+        expected_chat.sort_by_key(|(_, _, ms)| *ms);
+        assert_eq!(chat, expected_chat);
+
+        let turns: Vec<(&str, i64, i64)> = record.floor_log.iter()
+            .map(|t| (t.name.as_str(), (t.from - start).num_milliseconds(), (t.until - start).num_milliseconds()))
+            .collect();
+        assert_eq!(&turns[..6], &[("Mat", 0, 1500), ("Mat", 1500, 3000), ("Mat", 3000, 4500), ("Mat", 4500, 6000),
+                                  ("Student_1", 6000, 6000), ("Mat", 6000, 7600)]);
+        assert!(record.floor_log.iter().all(|turn| !turn.cut_off));
+    }
+
+    // Whatever order students ready together take their steps in, the same things happen as with a thread each:
+    // every concept is presented, every student asks about the tricky ones, and everybody leaves. Who gets the
+    // floor first, and so who gives up waiting for it and how much is said, depends on that order.
+    fn assert_same_session_as_with_a_thread_each(execution: Execution) {
+        let clock = Arc::new(SimulatedClock::starting_at_epoch());
+        let transcript = run_session(Scenario::default(), clock, execution).transcript;
+
+        assert!(transcript.events.windows(2).all(|pair| pair[0].timestamp() <= pair[1].timestamp()));
+        let presented: Vec<&str> = transcript.spoken().into_iter()
+            .filter(|u| u.text.starts_with("Let me tell you about"))
+            .map(|u| u.text.as_str())
+            .collect();
+        assert_eq!(presented, (1..=6).map(|n| format!("Let me tell you about Amazing thing #{}...", n))
+            .collect::<Vec<_>>());
+        let mut asked: Vec<(&str, &str)> = transcript.answers().into_iter()
+            .map(|a| (a.question.student.as_str(), a.question.concept.as_str()))
+            .collect();
+        asked.sort_unstable();
+        assert_eq!(asked, vec![
+            ("Student_1", "Amazing thing #3"), ("Student_1", "Amazing thing #6"),
+            ("Student_2", "Amazing thing #3"), ("Student_2", "Amazing thing #6"),
+            ("Student_3", "Amazing thing #3"), ("Student_3", "Amazing thing #6"),
+        ]);
+        let left = transcript.events.iter().filter(|e| matches!(e, Event::Left { .. })).count();
+        assert_eq!(left, 4);
+    }
+
+    #[test]
+    fn students_on_a_pool_go_through_the_same_session() {
+        assert_same_session_as_with_a_thread_each(Execution::Pool { workers: 2 });
+    }
+
+    #[test]
+    fn async_sessions_go_through_the_same_session() {
+        assert_same_session_as_with_a_thread_each(Execution::Async { threads: 3 });
+
+        // on a single thread, tasks take turns exactly like threads on the simulated clock
+        let session = |execution| {
+            run_session(Scenario::default(), Arc::new(SimulatedClock::starting_at_epoch()), execution).transcript.events
+        };
+        assert_eq!(session(Execution::Async { threads: 1 }), session(Execution::ThreadPerStudent));
+    }
+
+    #[test]
+    fn a_bounded_chat_lets_nothing_but_chat_messages_go() {
+        let session = |chat, execution| {
+            let scenario = Scenario { chat, ..Scenario::default() };
+            run_session(scenario, Arc::new(SimulatedClock::starting_at_epoch()), execution)
+        };
+        let bounded = |overflow| ChatConfig { capacity: Some(1), overflow, ..ChatConfig::default() };
+
+        // waiting for room takes no time at all in simulated time
+        let unbounded = session(ChatConfig::default(), Execution::ThreadPerStudent);
+        assert!(unbounded.metrics.chat_overflow.is_none());
+        let blocking = session(bounded(Overflow::Block), Execution::ThreadPerStudent);
+        assert_eq!(blocking.transcript.events, unbounded.transcript.events);
+        let overflow = blocking.metrics.chat_overflow.unwrap();
+        assert_eq!((overflow.dropped, overflow.coalesced), (0, 0));
+
+        for overflow in [Overflow::DropNewest, Overflow::DropOldest, Overflow::Coalesce].iter() {
+            for execution in [Execution::ThreadPerStudent, Execution::Async { threads: 1 }].iter() {
+                let record = session(bounded(*overflow), *execution);
+                let counts = record.metrics.chat_overflow.unwrap();
+                assert_eq!(record.transcript.spoken().len(), 16);
+                assert_eq!(record.transcript.chat().len() + counts.dropped + counts.coalesced, 12);
+            }
+        }
+    }
+
+    #[test]
+    fn private_messages_stay_out_of_the_transcript() {
+        let session = |whispering| {
+            let mut scenario = Scenario::default();
+            scenario.students.iter_mut().for_each(|profile| profile.whispering = whispering);
+            run_session(scenario, Arc::new(SimulatedClock::starting_at_epoch()), Execution::ThreadPerStudent)
+        };
+        let quiet = session(0.0);
+        let whispering = session(1.0);
+        assert_eq!(whispering.transcript.events, quiet.transcript.events);
+        assert_eq!(quiet.direct_messages, RoutingCounts::default());
+        // every student whispers about every concept that is not tricky, and the presenter answers what it gets
+        let counts = whispering.direct_messages;
+        assert!(counts.delivered + counts.undeliverable >= 3 * 4);
+    }
+
+    #[test]
+    fn breakout_rooms_are_opened_and_closed_in_the_middle_of_the_session() {
+        let session = |execution| {
+            let mut scenario = Scenario::default();
+            scenario.presenter.breakout = Some(BreakoutConfig { after_concept: 2, rooms: 2, duration_ms: 3000 });
+            run_session(scenario, Arc::new(SimulatedClock::starting_at_epoch()), execution).transcript
+        };
+        let transcript = session(Execution::ThreadPerStudent);
+        let start = transcript.events[0].timestamp();
+        let members = |names: &[&str]| names.iter().map(|name| String::from(*name)).collect::<Vec<_>>();
+
+        let breakout: Vec<_> = transcript.events.iter().filter_map(|event| match event {
+            Event::RoomsOpened { rooms, timestamp } => {
+                Some((0, rooms.clone(), (*timestamp - start).num_milliseconds()))
+            }
+            Event::RoomClosed { room, members, timestamp, .. } => {
+                Some((*room, vec![members.clone()], (*timestamp - start).num_milliseconds()))
+            }
+            _ => None,
+        }).collect();
+        // once done with concept #2, the presenter sends everybody off for 3 seconds
+        let rooms = vec![members(&["Student_1", "Student_3"]), members(&["Student_2"])];
+        assert_eq!(breakout, vec![
+            (0, rooms.clone(), 3000),
+            (1, vec![rooms[0].clone()], 6000),
+            (2, vec![rooms[1].clone()], 6000),
+        ]);
+        // everybody talked about concept #2 in their own room, and none of it made it to the main chat
+        for event in &transcript.events {
+            if let Event::RoomClosed { members, conversation, .. } = event {
+                assert!(members.iter().all(|name| conversation.iter().any(|msg| msg.username == *name)));
+                assert!(conversation.iter().all(|msg| members.contains(&msg.username)));
+                assert!(conversation.iter().all(|msg| msg.text.contains("Amazing thing #2")));
+            }
+        }
+        assert!(transcript.chat().iter().all(|msg| msg.text.ends_with("is cool!")));
+        let presented = transcript.spoken().iter().filter(|u| u.text.starts_with("Let me tell you about")).count();
+        assert_eq!(presented, 6);
+
+        // rooms come and go the same way whoever runs the students
+        for execution in [Execution::Pool { workers: 2 }, Execution::Async { threads: 3 }].iter() {
+            let transcript = session(*execution);
+            let closed = transcript.events.iter().filter(|e| matches!(e, Event::RoomClosed { .. })).count();
+            assert_eq!((closed, transcript.spoken().iter().filter(|u| u.text.starts_with("Let me")).count()), (2, 6));
+        }
+        assert_eq!(session(Execution::Async { threads: 1 }).events, transcript.events);
+    }
+
+    #[test]
+    fn a_seeded_session_goes_exactly_the_same_way_every_time() {
+        // chatty students react to what others said, so their choices depend on what they find in the feed
+        let session = || {
+            let scenario = Scenario::parse(include_str!("../scenarios/mixed_class.toml")).unwrap();
+            run_session(scenario, Arc::new(SimulatedClock::starting_at_epoch()), Execution::ThreadPerStudent)
+        };
+        let first = session();
+        assert!(first.transcript.events.iter().any(|event| matches!(event, Event::Reaction(_))));
+        for _ in 0..5 {
+            let again = session();
+            assert_eq!(again.transcript.events, first.transcript.events);
+            assert_eq!(again.direct_messages, first.direct_messages);
+        }
+    }
+
+    #[test]
+    fn the_summary_ends_with_how_long_the_session_took() {
+        let record = run_session(Scenario::default(), Arc::new(SimulatedClock::starting_at_epoch()),
+                                 Execution::ThreadPerStudent);
+        let summary = record.summary(&["transcript.jsonl", "metrics.json"]);
+        let sections = ["asked about", "private messages were delivered", "Chat delivery latency", "Who held the floor",
+                        "Wrote transcript.jsonl", "Wrote metrics.json", "End of training session after 12900ms"];
+        let positions: Vec<usize> = sections.iter()
+            .map(|section| summary.find(section).unwrap_or_else(|| panic!("no {:?} in the summary", section)))
+            .collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]), "out of order: {}", summary);
+        assert!(summary.lines().last().unwrap().contains("End of training session"));
+    }
+
+    #[test]
+    fn every_thread_of_the_session_is_counted() {
+        // the presenter, the event bus and the router, besides the students or the tasks
+        assert_eq!(Execution::ThreadPerStudent.threads(3, 0), 6);
+        assert_eq!(Execution::Pool { workers: 2 }.threads(3, 0), 6);
+        assert_eq!(Execution::Async { threads: 1 }.threads(3, 0), 1);
+        assert_eq!(Execution::Async { threads: 4 }.threads(3, 0), 4);
+        // and the breakout rooms, never more of them than students
+        let mixed = Scenario::parse(include_str!("../scenarios/mixed_class.toml")).unwrap();
+        assert_eq!(mixed.breakout_rooms(), 2);
+        assert_eq!(Execution::ThreadPerStudent.threads(4, mixed.breakout_rooms()), 9);
+        assert_eq!(mixed.with_class_size(1).breakout_rooms(), 1);
+        assert_eq!(Scenario::default().breakout_rooms(), 0);
+    }
+}
@@ -0,0 +1,96 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::sync::mpsc;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use crate::board::Cue;
+use crate::clock::ActorId;
+use crate::participants::{Participant, Stage, Step, Student};
+use crate::pool::ThreadPool;
+
+// Runs every student as a cooperative task on a pool of workers, instead of on a thread each.
+//
+// As far as the clock is concerned the whole class is a single actor: the scheduler hands every student who
+// is ready for their next step to the pool, waits for all of them to take it, and then waits itself, through
+// the clock, until the next student wakes up or the concept somebody waits for is announced. Students ready at
+// the same time take their steps in parallel, so unlike with a thread each, who goes first among them (say,
+// who gets the floor) can change from one run to the next, even in simulated time.
+pub(crate) fn run_on_pool(students: Vec<Student>, workers: usize, stage: Arc<Stage>, actor: ActorId) {
+    let pool = ThreadPool::new(workers);
+    let clock = Arc::clone(&stage.clock);
+    clock.start(actor);
+
+    // students are taken out while they are on the pool, and for good once they are done
+    let mut tasks: Vec<Option<Student>> = students.into_iter().map(Some).collect();
+    let mut remaining = tasks.len();
+    let mut ready: Vec<usize> = (0..tasks.len()).collect();
+    let mut sleeping: BinaryHeap<Reverse<(DateTime<Utc>, usize)>> = BinaryHeap::new();
+    let mut waiting: Vec<(usize, Cue)> = vec![];
+
+    while remaining > 0 {
+        if !ready.is_empty() {
+            let (steps_tx, steps_rx) = mpsc::channel();
+            let dispatched = ready.len();
+            for id in ready.drain(..) {
+                let mut student = tasks[id].take().unwrap();
+                let steps_tx = steps_tx.clone();
+                pool.execute(move || {
+                    let step = student.step();
+                    steps_tx.send((id, student, step)).unwrap();
+                });
+            }
+            drop(steps_tx);
+            // every sender is gone once every job is over, including any that panicked
+            let mut taken = 0;
+            for (id, student, step) in steps_rx {
+                taken += 1;
+                match step {
+                    Step::Sleep(duration) => {
+                        sleeping.push(Reverse((clock.now() + chrono::Duration::from_std(duration).unwrap(), id)));
+                        tasks[id] = Some(student);
+                    }
+                    Step::WaitFor(cue) => {
+                        waiting.push((id, cue));
+                        tasks[id] = Some(student);
+                    }
+                    // leaving drops every sender the student had
+                    Step::Done => remaining -= 1,
+                }
+            }
+            assert_eq!(taken, dispatched, "a student panicked on the thread pool");
+            continue;
+        }
+
+        let any_given = || waiting.iter().any(|&(_, cue)| stage.is_given(cue));
+        match sleeping.peek() {
+            Some(Reverse((wake_at, _))) => {
+                let timeout = (*wake_at - clock.now()).to_std().unwrap_or_default();
+                clock.park_timeout(actor, &any_given, timeout);
+            }
+            None => clock.park(actor, &any_given),
+        }
+
+        let now = clock.now();
+        while let Some(Reverse((wake_at, id))) = sleeping.peek().copied() {
+            if wake_at > now {
+                break;
+            }
+            sleeping.pop();
+            ready.push(id);
+        }
+        waiting.retain(|&(id, cue)| {
+            let given = stage.is_given(cue);
+            if given {
+                ready.push(id);
+            }
+            !given
+        });
+    }
+
+    // the workers are idle by now
+    drop(pool);
+    drop(stage);
+    clock.finish(actor);
+}
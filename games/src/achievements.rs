@@ -0,0 +1,249 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{Duration, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::game::{GameResult, Mode};
+use crate::storage;
+
+const ACHIEVEMENTS_FILE: &str = "achievements.json";
+
+const BANNER_WIDTH: usize = 50;
+
+// What a player has to do to unlock an achievement
+enum Condition {
+    GuessWonWithin { tries: u32 },
+    Remembered { numbers: usize },
+    WonInMode(Mode),
+    GamesPlayed(u32),
+    DaysInARow(u32),
+}
+
+// Name and description come from the "achievement.<id>.name" and "achievement.<id>.description" messages
+struct Achievement {
+    id: &'static str,
+    condition: Condition,
+}
+
+const ACHIEVEMENTS: [Achievement; 8] = [
+    Achievement { id: "first_win", condition: Condition::GuessWonWithin { tries: u32::MAX } },
+    Achievement { id: "sharpshooter", condition: Condition::GuessWonWithin { tries: 3 } },
+    Achievement { id: "good_memory", condition: Condition::Remembered { numbers: 5 } },
+    Achievement { id: "elephant_memory", condition: Condition::Remembered { numbers: 15 } },
+    Achievement { id: "beat_the_clock", condition: Condition::WonInMode(Mode::Timed) },
+    Achievement { id: "blitz_champion", condition: Condition::WonInMode(Mode::Blitz) },
+    Achievement { id: "regular", condition: Condition::GamesPlayed(25) },
+    Achievement { id: "week_streak", condition: Condition::DaysInARow(7) },
+];
+
+// Everything an achievement can be judged on, right after a game
+struct Context<'a> {
+    result: &'a GameResult,
+    games_played: u32,
+    days_in_a_row: u32,
+}
+
+impl Condition {
+    fn is_met(&self, context: &Context) -> bool {
+        match (self, context.result) {
+            (Condition::GuessWonWithin { tries }, GameResult::Guess(result)) => result.won && result.tries <= *tries,
+            (Condition::Remembered { numbers }, GameResult::Remember(result)) => result.remembered >= *numbers,
+            (Condition::WonInMode(mode), result) => result.mode() == *mode && result.won(),
+            (Condition::GamesPlayed(games), _) => context.games_played >= *games,
+            (Condition::DaysInARow(days), _) => context.days_in_a_row >= *days,
+            _ => false,
+        }
+    }
+}
+
+fn name(id: &str) -> &'static str {
+    crate::i18n::message(&format!("achievement.{}.name", id))
+}
+
+fn description(id: &str) -> &'static str {
+    crate::i18n::message(&format!("achievement.{}.description", id))
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct PlayerAchievements {
+    // achievement id -> day it was unlocked
+    unlocked: BTreeMap<String, NaiveDate>,
+    days_played: BTreeSet<NaiveDate>,
+}
+
+impl PlayerAchievements {
+    pub(crate) fn load(player: &str) -> Self {
+        let mut all: BTreeMap<String, PlayerAchievements> = storage::load(ACHIEVEMENTS_FILE).unwrap_or_else(|e| {
+            println!("{}", tr!("achievements.load_error", error = e));
+            BTreeMap::new()
+        });
+        all.remove(player).unwrap_or_default()
+    }
+
+    // Every player's achievements are in the same file, so nothing is saved if it cannot be read
+    fn save(self, player: &str) {
+        let mut all: BTreeMap<String, PlayerAchievements> = match storage::load(ACHIEVEMENTS_FILE) {
+            Ok(all) => all,
+            Err(e) => {
+                println!("{}", tr!("achievements.load_error", error = e));
+                return;
+            }
+        };
+        all.insert(player.to_string(), self);
+        if let Err(e) = storage::save(ACHIEVEMENTS_FILE, &all) {
+            println!("{}", tr!("achievements.save_error", error = e));
+        }
+    }
+
+    pub(crate) fn is_unlocked(&self, id: &str) -> bool {
+        self.unlocked.contains_key(id)
+    }
+
+    // consecutive days played, counting back from the most recent one
+    fn days_in_a_row(&self) -> u32 {
+        let mut streak = 0;
+        let mut expected: Option<NaiveDate> = None;
+        for &day in self.days_played.iter().rev() {
+            if expected.is_some_and(|expected| day != expected) {
+                break;
+            }
+            streak += 1;
+            expected = Some(day - Duration::days(1));
+        }
+        streak
+    }
+
+    pub(crate) fn print(&self) {
+        println!();
+        println!("{}", tr!("achievements.title", unlocked = self.unlocked.len(), total = ACHIEVEMENTS.len()));
+        println!();
+        for achievement in ACHIEVEMENTS.iter() {
+            let status = match self.unlocked.get(achievement.id) {
+                Some(date) => format!("★ {}", date),
+                None => "☆".to_string(),
+            };
+            println!("{} {} - {}", status, name(achievement.id), description(achievement.id));
+        }
+    }
+}
+
+// Judges every achievement not yet unlocked against the game just played, announcing the new ones
+pub(crate) fn evaluate(player: &str, result: &GameResult, games_played: u32) {
+    let mut achievements = PlayerAchievements::load(player);
+    let today = Local::now().date_naive();
+    achievements.days_played.insert(today);
+
+    let context = Context {
+        result,
+        games_played,
+        days_in_a_row: achievements.days_in_a_row(),
+    };
+    for achievement in ACHIEVEMENTS.iter() {
+        if !achievements.is_unlocked(achievement.id) && achievement.condition.is_met(&context) {
+            achievements.unlocked.insert(achievement.id.to_string(), today);
+            print_banner(achievement.id);
+        }
+    }
+    achievements.save(player);
+}
+
+fn print_banner(id: &str) {
+    let border = "★".repeat(BANNER_WIDTH);
+    println!();
+    println!("{}", border);
+    println!("  {}", tr!("achievements.unlocked", name = name(id)));
+    println!("  {}", description(id));
+    println!("{}", border);
+}
+
+// Harder modes have to be earned first
+pub(crate) fn required_for(mode: Mode) -> Option<&'static str> {
+    match mode {
+        Mode::Classic => None,
+        Mode::Timed => Some("first_win"),
+        Mode::Blitz => Some("beat_the_clock"),
+    }
+}
+
+pub(crate) fn locked_hint(id: &str) -> String {
+    tr!("achievements.locked", name = name(id))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::guess_the_number::GuessResult;
+    use crate::remember_numbers::RememberResult;
+
+    use super::*;
+
+    fn day(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn played_on(days: &[NaiveDate]) -> PlayerAchievements {
+        let mut achievements = PlayerAchievements::default();
+        achievements.days_played.extend(days.iter().copied());
+        achievements
+    }
+
+    fn guess(mode: Mode, won: bool, tries: u32) -> GameResult {
+        GameResult::Guess(GuessResult {
+            mode,
+            won,
+            max_number: 100,
+            tries,
+            hints: vec![],
+            elapsed: std::time::Duration::from_secs(30),
+        })
+    }
+
+    fn context(result: &GameResult) -> Context<'_> {
+        Context { result, games_played: 1, days_in_a_row: 1 }
+    }
+
+    #[test]
+    fn days_in_a_row_count_back_from_the_last_day_played_until_a_gap() {
+        assert_eq!(played_on(&[]).days_in_a_row(), 0);
+        assert_eq!(played_on(&[day(2024, 3, 5)]).days_in_a_row(), 1);
+        assert_eq!(played_on(&[day(2024, 3, 1), day(2024, 3, 3), day(2024, 3, 4), day(2024, 3, 5)]).days_in_a_row(), 3);
+        // a gap just before the last day ends the streak there
+        assert_eq!(played_on(&[day(2024, 3, 3), day(2024, 3, 4), day(2024, 3, 6)]).days_in_a_row(), 1);
+    }
+
+    #[test]
+    fn playing_twice_on_a_day_counts_it_once() {
+        let mut achievements = played_on(&[day(2024, 3, 4), day(2024, 3, 5)]);
+        achievements.days_played.insert(day(2024, 3, 5));
+        assert_eq!(achievements.days_in_a_row(), 2);
+    }
+
+    #[test]
+    fn streaks_go_on_across_months_years_and_leap_days() {
+        assert_eq!(played_on(&[day(2024, 1, 31), day(2024, 2, 1)]).days_in_a_row(), 2);
+        assert_eq!(played_on(&[day(2023, 12, 30), day(2023, 12, 31), day(2024, 1, 1)]).days_in_a_row(), 3);
+        assert_eq!(played_on(&[day(2024, 2, 28), day(2024, 2, 29), day(2024, 3, 1)]).days_in_a_row(), 3);
+        // no 29th of February in 2023
+        assert_eq!(played_on(&[day(2023, 2, 27), day(2023, 3, 1)]).days_in_a_row(), 1);
+    }
+
+    #[test]
+    fn conditions_are_only_met_by_the_games_they_are_about() {
+        let sharp = Condition::GuessWonWithin { tries: 3 };
+        assert!(sharp.is_met(&context(&guess(Mode::Classic, true, 3))));
+        assert!(!sharp.is_met(&context(&guess(Mode::Classic, true, 4))));
+        assert!(!sharp.is_met(&context(&guess(Mode::Blitz, false, 2))));
+        let remember = GameResult::Remember(RememberResult {
+            mode: Mode::Timed,
+            remembered: 5,
+            elapsed: std::time::Duration::from_secs(30),
+        });
+        assert!(!sharp.is_met(&context(&remember)));
+        assert!(Condition::Remembered { numbers: 5 }.is_met(&context(&remember)));
+        assert!(Condition::WonInMode(Mode::Timed).is_met(&context(&remember)));
+        assert!(!Condition::WonInMode(Mode::Timed).is_met(&context(&guess(Mode::Classic, true, 1))));
+
+        let regular = Context { games_played: 25, days_in_a_row: 6, ..context(&remember) };
+        assert!(Condition::GamesPlayed(25).is_met(&regular));
+        assert!(!Condition::DaysInARow(7).is_met(&regular));
+    }
+}
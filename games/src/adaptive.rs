@@ -0,0 +1,215 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Difficulty, GameResult};
+use crate::remember_numbers::Alphabet;
+use crate::stats::REMEMBER_WIN_THRESHOLD;
+use crate::storage;
+
+const ADAPTIVE_FILE: &str = "adaptive.json";
+
+// The difficulty is adjusted so that players win roughly this fraction of their games
+const TARGET_SUCCESS_RATE: f64 = 0.7;
+const STEP: f64 = 1.0;
+const MIN_LEVEL: f64 = -4.0;
+const MAX_LEVEL: f64 = 12.0;
+// how many recent games are kept around, just to show them in the debug screen
+const HISTORY_LEN: usize = 10;
+
+// guess_the_number is won if it takes at most this many tries more than a perfect binary search
+const EXTRA_TRIES_ALLOWED: u32 = 2;
+
+// A weighted up/down staircase: every win raises the level by STEP * (1 - target), every loss lowers
+// it by STEP * target, so the level settles where the player wins a `target` fraction of their games.
+#[derive(Default, Serialize, Deserialize)]
+struct Staircase {
+    level: f64,
+    recent: VecDeque<bool>,
+}
+
+impl Staircase {
+    fn update(&mut self, success: bool) {
+        let change = if success {
+            STEP * (1.0 - TARGET_SUCCESS_RATE)
+        } else {
+            -STEP * TARGET_SUCCESS_RATE
+        };
+        self.level = (self.level + change).clamp(MIN_LEVEL, MAX_LEVEL);
+        self.recent.push_back(success);
+        if self.recent.len() > HISTORY_LEN {
+            self.recent.pop_front();
+        }
+    }
+
+    fn print_history(&self) {
+        if self.recent.is_empty() {
+            println!("  {}", tr!("adaptive.no_history"));
+            return;
+        }
+        let history: String = self.recent.iter().map(|&won| if won { '✓' } else { '✗' }).collect();
+        let wins = self.recent.iter().filter(|&&won| won).count();
+        let rate = 100 * wins / self.recent.len();
+        println!("  {}", tr!("adaptive.recent", history = history, rate = rate));
+    }
+}
+
+// The fewest tries that always find a number between 1 and max_number
+fn optimal_tries(max_number: u32) -> u32 {
+    32 - max_number.leading_zeros()
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct AdaptiveModel {
+    guess: Staircase,
+    remember: Staircase,
+}
+
+impl AdaptiveModel {
+    pub(crate) fn load(player: &str) -> Self {
+        let mut models: BTreeMap<String, AdaptiveModel> = storage::load(ADAPTIVE_FILE).unwrap_or_else(|e| {
+            println!("{}", tr!("adaptive.load_error", error = e));
+            BTreeMap::new()
+        });
+        models.remove(player).unwrap_or_default()
+    }
+
+    // Every player's model is in the same file, so nothing is saved if it cannot be read
+    pub(crate) fn save(self, player: &str) {
+        let mut models: BTreeMap<String, AdaptiveModel> = match storage::load(ADAPTIVE_FILE) {
+            Ok(models) => models,
+            Err(e) => {
+                println!("{}", tr!("adaptive.load_error", error = e));
+                return;
+            }
+        };
+        models.insert(player.to_string(), self);
+        if let Err(e) = storage::save(ADAPTIVE_FILE, &models) {
+            println!("{}", tr!("adaptive.save_error", error = e));
+        }
+    }
+
+    // Every level up makes guess_the_number's range 25% wider. For remember_numbers it shows every
+    // symbol for 15% less time, and from level 4 (and 8) adds letters to the alphabet.
+    pub(crate) fn difficulty(&self) -> Difficulty {
+        let classic = Difficulty::default();
+        let guess_level = self.guess.level.floor();
+        let remember_level = self.remember.level.floor();
+        let alphabet = if remember_level >= 8.0 {
+            Alphabet::Alphanumeric
+        } else if remember_level >= 4.0 {
+            Alphabet::Hexadecimal
+        } else {
+            Alphabet::Digits
+        };
+        Difficulty {
+            max_number: (f64::from(classic.max_number) * 1.25f64.powf(guess_level)).round() as u32,
+            reveal_time: classic.reveal_time.mul_f64(0.85f64.powf(remember_level)).max(Duration::from_millis(200)),
+            alphabet,
+        }
+    }
+
+    pub(crate) fn update(&mut self, result: &GameResult) {
+        match result {
+            GameResult::Guess(result) => {
+                let allowed = optimal_tries(result.max_number) + EXTRA_TRIES_ALLOWED;
+                self.guess.update(result.won && result.tries <= allowed);
+            }
+            GameResult::Remember(result) => {
+                self.remember.update(result.remembered >= REMEMBER_WIN_THRESHOLD);
+            }
+        }
+    }
+
+    pub(crate) fn print_debug(&self, player: &str) {
+        let difficulty = self.difficulty();
+        println!();
+        println!("{}", tr!("adaptive.title", player = player, target = (TARGET_SUCCESS_RATE * 100.0).round()));
+        println!();
+        println!("{}", tr!("adaptive.guess",
+                           level = format!("{:.2}", self.guess.level),
+                           max = difficulty.max_number,
+                           tries = optimal_tries(difficulty.max_number) + EXTRA_TRIES_ALLOWED));
+        self.guess.print_history();
+        println!("{}", tr!("adaptive.remember",
+                           level = format!("{:.2}", self.remember.level),
+                           millis = difficulty.reveal_time.as_millis(),
+                           symbols = difficulty.alphabet.symbols()));
+        self.remember.print_history();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::Mode;
+    use crate::guess_the_number::GuessResult;
+
+    use super::*;
+
+    fn guess(won: bool, tries: u32) -> GameResult {
+        GameResult::Guess(GuessResult {
+            mode: Mode::Classic,
+            won,
+            max_number: 100,
+            tries,
+            hints: vec![],
+            elapsed: Duration::from_secs(30),
+        })
+    }
+
+    #[test]
+    fn winning_the_target_fraction_of_games_keeps_the_level_where_it_is() {
+        let mut staircase = Staircase::default();
+        for _ in 0..5 {
+            for success in [true, true, false, true, true, false, true, true, false, true].iter() {
+                staircase.update(*success);
+            }
+        }
+        assert!(staircase.level.abs() < 1e-9, "level {}", staircase.level);
+        assert_eq!(staircase.recent.len(), HISTORY_LEN);
+    }
+
+    #[test]
+    fn the_level_stays_within_bounds() {
+        let mut staircase = Staircase::default();
+        for _ in 0..100 {
+            staircase.update(true);
+        }
+        assert_eq!(staircase.level, MAX_LEVEL);
+        for _ in 0..100 {
+            staircase.update(false);
+        }
+        assert_eq!(staircase.level, MIN_LEVEL);
+    }
+
+    #[test]
+    fn a_guessing_game_only_counts_as_a_success_within_the_tries_allowed() {
+        assert_eq!(optimal_tries(100), 7);
+        assert_eq!(optimal_tries(128), 8);
+        let mut model = AdaptiveModel::default();
+        model.update(&guess(true, 9));
+        model.update(&guess(true, 10));
+        model.update(&guess(false, 3));
+        assert_eq!(model.guess.recent, vec![true, false, false]);
+    }
+
+    #[test]
+    fn higher_levels_make_the_games_harder() {
+        let mut model = AdaptiveModel::default();
+        assert_eq!(model.difficulty().max_number, 100);
+        assert!(model.difficulty().alphabet == Alphabet::Digits);
+
+        model.guess.level = 2.5;
+        model.remember.level = 4.0;
+        let difficulty = model.difficulty();
+        // only whole levels count
+        assert_eq!(difficulty.max_number, 156);
+        assert!(difficulty.alphabet == Alphabet::Hexadecimal);
+        assert!(difficulty.reveal_time < Difficulty::default().reveal_time);
+
+        model.remember.level = MAX_LEVEL;
+        assert!(model.difficulty().alphabet == Alphabet::Alphanumeric);
+        assert_eq!(model.difficulty().reveal_time, Duration::from_millis(200));
+    }
+}
@@ -0,0 +1,143 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::process::exit;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::game::Game;
+use crate::guess_the_number::Hint;
+use crate::input::{clear_screen, read_line};
+use crate::protocol::{ClientMessage, ServerMessage};
+
+const QUIT_COMMAND: &str = "quit";
+
+// how long a remember_numbers duel symbol stays on screen
+const REVEAL_TIME: Duration = Duration::from_millis(1000);
+
+// What the player is doing, which decides what their typing means
+enum Phase {
+    Lobby,
+    Guessing { tries: u32 },
+    Remembering,
+}
+
+fn print_lobby_help() {
+    println!();
+    println!("{}", tr!("client.lobby_help", command = QUIT_COMMAND));
+}
+
+// Runs on its own thread, showing everything the server says and keeping track of the phase
+fn show_messages(reader: BufReader<TcpStream>, phase: Arc<Mutex<Phase>>) {
+    let mut game = Game::GuessTheNumber;
+    for line in reader.lines() {
+        let message = match line.map(|line| ServerMessage::parse(&line)) {
+            Ok(Ok(message)) => message,
+            Ok(Err(e)) => {
+                println!("{}", tr!("client.bad_message", error = e));
+                continue;
+            }
+            Err(_) => break,
+        };
+        let mut phase = phase.lock().unwrap();
+        match message {
+            ServerMessage::Welcome(name) => {
+                println!("{}", tr!("client.welcome", name = name));
+                print_lobby_help();
+            }
+            ServerMessage::Lobby(names) => println!("{}", tr!("client.lobby", names = names.join(", "))),
+            ServerMessage::Started(started, players, max_number) => {
+                game = started;
+                println!();
+                match game {
+                    Game::GuessTheNumber => {
+                        *phase = Phase::Guessing { tries: 0 };
+                        println!("{}", tr!("client.race_started", players = players));
+                        println!("{}", tr!("guess.range", max = max_number));
+                        println!("{}", tr!("guess.prompt", number = 1));
+                    }
+                    Game::RememberNumbers => {
+                        *phase = Phase::Remembering;
+                        println!("{}", tr!("client.duel_started", players = players));
+                    }
+                }
+            }
+            ServerMessage::Hint(hint) => match (hint, &mut *phase) {
+                (Hint::Correct, Phase::Remembering) => println!("{}", tr!("client.wait_for_others")),
+                (Hint::Correct, _) => {}
+                (hint, Phase::Guessing { tries }) => {
+                    *tries += 1;
+                    match hint {
+                        Hint::TooSmall => println!("{}", tr!("guess.too_small")),
+                        _ => println!("{}", tr!("guess.too_big")),
+                    }
+                    println!("{}", tr!("guess.prompt", number = *tries + 1));
+                }
+                _ => {}
+            },
+            ServerMessage::Show(symbol) => {
+                // the player can still type while the symbol is on screen
+                drop(phase);
+                clear_screen();
+                println!("{}", tr!("remember.computer_number", number = symbol));
+                thread::sleep(REVEAL_TIME);
+                clear_screen();
+                println!("{}", tr!("client.duel_prompt"));
+            }
+            ServerMessage::Out(name) => println!("{}", tr!("client.out", name = name)),
+            ServerMessage::Winner(name, score) => {
+                match game {
+                    Game::GuessTheNumber => println!("{}", trn!("client.race_winner", score, name = name)),
+                    Game::RememberNumbers => println!("{}", trn!("client.duel_winner", score, name = name)),
+                }
+                *phase = Phase::Lobby;
+                print_lobby_help();
+            }
+            ServerMessage::Draw(score) => {
+                println!("{}", trn!("client.draw", score));
+                *phase = Phase::Lobby;
+                print_lobby_help();
+            }
+            ServerMessage::Error(error) => println!("{}", tr!("client.server_error", error = error)),
+        }
+    }
+    println!("{}", tr!("client.disconnected"));
+    exit(0);
+}
+
+pub(crate) fn join(addr: &str, name: &str) -> io::Result<()> {
+    let mut stream = TcpStream::connect(addr)?;
+    let reader = BufReader::new(stream.try_clone()?);
+    let phase = Arc::new(Mutex::new(Phase::Lobby));
+    let reader_phase = Arc::clone(&phase);
+    thread::spawn(move || show_messages(reader, reader_phase));
+
+    writeln!(stream, "{}", ClientMessage::Hello(name.to_string()).to_line())?;
+    loop {
+        let input = read_line();
+        let input = input.trim();
+        if input.eq_ignore_ascii_case(QUIT_COMMAND) {
+            writeln!(stream, "{}", ClientMessage::Quit.to_line())?;
+            return Ok(());
+        }
+        let message = match *phase.lock().unwrap() {
+            Phase::Lobby => match input {
+                "1" => ClientMessage::Start(Game::GuessTheNumber),
+                "2" => ClientMessage::Start(Game::RememberNumbers),
+                _ => {
+                    print_lobby_help();
+                    continue;
+                }
+            },
+            Phase::Guessing { .. } => match input.parse() {
+                Ok(guess) => ClientMessage::Guess(guess),
+                Err(_) => {
+                    println!("{}", tr!("input.only_numbers"));
+                    continue;
+                }
+            },
+            Phase::Remembering => ClientMessage::Sequence(input.to_uppercase()),
+        };
+        writeln!(stream, "{}", message.to_line())?;
+    }
+}
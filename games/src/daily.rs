@@ -0,0 +1,169 @@
+use std::io;
+
+use chrono::{Local, NaiveDate};
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Difficulty, Game, GameResult, GameRng, GameState, Mode};
+use crate::guess_the_number::{GuessResult, Hint};
+use crate::remember_numbers::RememberResult;
+use crate::savegame::{play_or_save, saved_game};
+use crate::storage;
+
+const DAILY_FILE: &str = "daily.json";
+
+#[derive(Serialize, Deserialize)]
+struct DailyAttempt {
+    date: String,
+    game: String,
+    player: String,
+    summary: String,
+}
+
+// FNV-1a over "<date>/<game>". Unlike the std hashers its output is guaranteed to never change,
+// so every teammate gets the same puzzle on the same day, whatever build they are running.
+pub(crate) fn seed_for(date: NaiveDate, game: Game) -> u64 {
+    let key = format!("{}/{}", date.format("%Y-%m-%d"), game.name());
+    key.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn guess_summary(date: NaiveDate, result: &GuessResult) -> String {
+    let grid: String = result.hints.iter()
+        .map(|hint| match hint {
+            Hint::TooSmall => "🔼",
+            Hint::TooBig => "🔽",
+            Hint::Correct => "🟩",
+        })
+        .collect();
+    let title = trn!("daily.guess_summary", result.tries, date = date, seconds = result.elapsed.as_secs());
+    format!("{}\n{}", title, grid)
+}
+
+fn remember_summary(date: NaiveDate, result: &RememberResult) -> String {
+    let grid = format!("{}🟥", "🟩".repeat(result.remembered));
+    let title = trn!("daily.remember_summary", result.remembered, date = date, seconds = result.elapsed.as_secs());
+    format!("{}\n{}", title, grid)
+}
+
+// An unreadable file is left alone rather than overwritten, so that no history is ever lost
+fn load_attempts() -> io::Result<Vec<DailyAttempt>> {
+    storage::load(DAILY_FILE)
+}
+
+fn is_attempt(attempt: &DailyAttempt, date: &str, game: Game, player: &str) -> bool {
+    attempt.date == date && attempt.game == game.name() && attempt.player == player
+}
+
+// Returns None when the player already had their go at today's challenge, or saved it for later
+pub(crate) fn daily_challenge(game: Game, player: &str) -> Option<GameResult> {
+    let date = Local::now().date_naive();
+    let date_str = date.format("%Y-%m-%d").to_string();
+
+    println!();
+    println!("{}", tr!("daily.title", date = date_str, game = game.name()));
+
+    // a saved challenge has its attempt recorded already
+    if let Some(saved) = saved_game(player) {
+        if saved.daily == Some(date) && saved.state.game() == game {
+            println!("{}", tr!("daily.saved"));
+            return None;
+        }
+    }
+    let mut attempts = match load_attempts() {
+        Ok(attempts) => attempts,
+        Err(e) => {
+            println!("{}", tr!("daily.load_error", error = e));
+            return None;
+        }
+    };
+    if let Some(previous) = attempts.iter().find(|a| is_attempt(a, &date_str, game, player)) {
+        println!("{}", tr!("daily.already_played"));
+        println!();
+        println!("{}", previous.summary);
+        return None;
+    }
+
+    // recorded before playing, so that quitting half way does not give the player another go
+    attempts.push(DailyAttempt {
+        date: date_str.clone(),
+        game: game.name().to_string(),
+        player: player.to_string(),
+        summary: tr!("daily.unfinished", date = date_str, game = game.name()),
+    });
+    if let Err(e) = storage::save(DAILY_FILE, &attempts) {
+        println!("{}", tr!("daily.save_error", error = e));
+        return None;
+    }
+
+    let generator = GameRng::seed_from_u64(seed_for(date, game));
+    play_daily(GameState::new(game, Mode::Classic, Difficulty::default(), generator), date, player)
+}
+
+// Plays (or carries on with) the daily challenge of the given date, and records the result once finished
+pub(crate) fn play_daily(state: GameState, date: NaiveDate, player: &str) -> Option<GameResult> {
+    let game = state.game();
+    let result = play_or_save(state, player, Some(date))?;
+    let summary = match &result {
+        GameResult::Guess(result) => guess_summary(date, result),
+        GameResult::Remember(result) => remember_summary(date, result),
+    };
+
+    println!();
+    println!("{}", tr!("daily.share"));
+    println!();
+    println!("{}", summary);
+
+    let mut attempts = match load_attempts() {
+        Ok(attempts) => attempts,
+        Err(e) => {
+            println!("{}", tr!("daily.load_error", error = e));
+            return Some(result);
+        }
+    };
+    let date_str = date.format("%Y-%m-%d").to_string();
+    match attempts.iter_mut().find(|a| is_attempt(a, &date_str, game, player)) {
+        Some(attempt) => attempt.summary = summary,
+        None => attempts.push(DailyAttempt {
+            date: date_str,
+            game: game.name().to_string(),
+            player: player.to_string(),
+            summary,
+        }),
+    }
+    if let Err(e) = storage::save(DAILY_FILE, &attempts) {
+        println!("{}", tr!("daily.save_error", error = e));
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn the_seed_never_changes_for_a_given_day_and_game() {
+        let seed = seed_for(date(2024, 3, 1), Game::GuessTheNumber);
+        assert_eq!(seed, seed_for(date(2024, 3, 1), Game::GuessTheNumber));
+        // every build, on every machine, today and in years to come
+        assert_eq!(seed, 0xe9ce_166c_5c6e_8f4f);
+    }
+
+    #[test]
+    fn every_day_and_every_game_gets_a_seed_of_its_own() {
+        let seeds = [
+            seed_for(date(2024, 3, 1), Game::GuessTheNumber),
+            seed_for(date(2024, 3, 1), Game::RememberNumbers),
+            seed_for(date(2024, 3, 2), Game::GuessTheNumber),
+            seed_for(date(2025, 3, 1), Game::GuessTheNumber),
+        ];
+        for (idx, seed) in seeds.iter().enumerate() {
+            assert!(!seeds[idx + 1..].contains(seed), "seed #{} is not unique", idx + 1);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use rand::SeedableRng;
+use rand_pcg::Pcg64;
+use serde::{Deserialize, Serialize};
+
+use crate::guess_the_number::{guess_the_number, GuessResult, GuessState};
+use crate::input::SAVE_COMMAND;
+use crate::remember_numbers::{remember_numbers, Alphabet, RememberResult, RememberState};
+use crate::stats::REMEMBER_WIN_THRESHOLD;
+
+// A generator whose state can be saved along with the game, unlike rand::thread_rng()
+pub(crate) type GameRng = Pcg64;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum Game {
+    GuessTheNumber,
+    RememberNumbers,
+}
+
+impl Game {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Game::GuessTheNumber => "guess_the_number",
+            Game::RememberNumbers => "remember_numbers",
+        }
+    }
+}
+
+// How hard a game is, tuned to every player by the adaptive module.
+// The default is the classic game, which is what everybody plays in the daily challenge.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct Difficulty {
+    // guess_the_number picks a number between 1 and this
+    pub(crate) max_number: u32,
+    // how long remember_numbers shows every new symbol for
+    pub(crate) reveal_time: Duration,
+    pub(crate) alphabet: Alphabet,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Self {
+            max_number: 100,
+            reveal_time: Duration::from_millis(1000),
+            alphabet: Alphabet::Digits,
+        }
+    }
+}
+
+// total time for a whole game in blitz mode
+pub(crate) const BLITZ_TIME_BUDGET: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum Mode {
+    Classic,
+    // every answer has its own time limit
+    Timed,
+    // the whole game has to fit in BLITZ_TIME_BUDGET
+    Blitz,
+}
+
+impl Mode {
+    pub(crate) fn label(self) -> String {
+        match self {
+            Mode::Classic => tr!("mode.classic"),
+            Mode::Timed => tr!("mode.timed"),
+            Mode::Blitz => tr!("mode.blitz", seconds = BLITZ_TIME_BUDGET.as_secs()),
+        }
+    }
+
+    // How long the player has for their next answer, if there is any limit
+    pub(crate) fn time_limit(self, per_answer: Duration, elapsed: Duration) -> Option<Duration> {
+        match self {
+            Mode::Classic => None,
+            Mode::Timed => Some(per_answer),
+            Mode::Blitz => Some(BLITZ_TIME_BUDGET.checked_sub(elapsed).unwrap_or_default()),
+        }
+    }
+}
+
+pub(crate) enum GameResult {
+    Guess(GuessResult),
+    Remember(RememberResult),
+}
+
+impl GameResult {
+    pub(crate) fn mode(&self) -> Mode {
+        match self {
+            GameResult::Guess(result) => result.mode,
+            GameResult::Remember(result) => result.mode,
+        }
+    }
+
+    // remembering at least REMEMBER_WIN_THRESHOLD numbers counts as a win
+    pub(crate) fn won(&self) -> bool {
+        match self {
+            GameResult::Guess(result) => result.won,
+            GameResult::Remember(result) => result.remembered >= REMEMBER_WIN_THRESHOLD,
+        }
+    }
+}
+
+// Everything needed to carry on with a game exactly where it was left
+#[derive(Serialize, Deserialize)]
+pub(crate) enum GameState {
+    Guess(GuessState),
+    Remember(RememberState),
+}
+
+impl GameState {
+    pub(crate) fn new(game: Game, mode: Mode, difficulty: Difficulty, mut generator: GameRng) -> Self {
+        match game {
+            Game::GuessTheNumber => GameState::Guess(GuessState::new(mode, difficulty, &mut generator)),
+            Game::RememberNumbers => GameState::Remember(RememberState::new(mode, difficulty, generator)),
+        }
+    }
+
+    // a fresh game, seeded from the thread-local random number generator
+    pub(crate) fn random(game: Game, mode: Mode, difficulty: Difficulty) -> Self {
+        let generator = GameRng::from_rng(rand::thread_rng()).expect("Failed to seed generator");
+        Self::new(game, mode, difficulty, generator)
+    }
+
+    pub(crate) fn game(&self) -> Game {
+        match self {
+            GameState::Guess(_) => Game::GuessTheNumber,
+            GameState::Remember(_) => Game::RememberNumbers,
+        }
+    }
+
+    // Returns None if the player chose to save and quit
+    pub(crate) fn play(&mut self) -> Option<GameResult> {
+        println!("{}", tr!("game.save_hint", command = SAVE_COMMAND));
+        match self {
+            GameState::Guess(state) => guess_the_number(state).map(GameResult::Guess),
+            GameState::Remember(state) => remember_numbers(state).map(GameResult::Remember),
+        }
+    }
+}
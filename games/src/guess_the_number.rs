@@ -0,0 +1,136 @@
+use std::cmp::Ordering;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Difficulty, Mode};
+use crate::input::{read_game_line, GameInput};
+
+// time for every guess in timed mode
+const TIME_PER_GUESS: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub(crate) enum Hint {
+    TooSmall,
+    TooBig,
+    Correct,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct GuessState {
+    mode: Mode,
+    #[serde(default)]
+    difficulty: Difficulty,
+    secret_number: u32,
+    try_number: u32,
+    hints: Vec<Hint>,
+    // time spent playing, not counting the time the game was saved for
+    elapsed: Duration,
+}
+
+impl GuessState {
+    pub(crate) fn new<R: Rng>(mode: Mode, difficulty: Difficulty, generator: &mut R) -> Self {
+        Self {
+            mode,
+            difficulty,
+            // the gen_range method is defined by the Rng trait that we brought into scope with the
+            // use rand::Rng statement
+            secret_number: generator.gen_range(1, difficulty.max_number + 1),
+            try_number: 0,
+            hints: vec![],
+            elapsed: Duration::from_secs(0),
+        }
+    }
+
+    fn result(&self, won: bool) -> GuessResult {
+        GuessResult {
+            mode: self.mode,
+            won,
+            max_number: self.difficulty.max_number,
+            tries: self.try_number,
+            hints: self.hints.clone(),
+            elapsed: self.elapsed,
+        }
+    }
+}
+
+pub(crate) struct GuessResult {
+    pub(crate) mode: Mode,
+    // only blitz games can be lost, by running out of time
+    pub(crate) won: bool,
+    pub(crate) max_number: u32,
+    pub(crate) tries: u32,
+    // how every valid guess compared to the secret number, in order
+    pub(crate) hints: Vec<Hint>,
+    pub(crate) elapsed: Duration,
+}
+
+// Returns None if the player chose to save and quit, in which case `state` holds the progress so far
+pub(crate) fn guess_the_number(state: &mut GuessState) -> Option<GuessResult> {
+    println!();
+    println!("{}", tr!("guess.title"));
+    println!("{}", tr!("guess.range", max = state.difficulty.max_number));
+    println!();
+
+    // println!("The secret number is: {}", state.secret_number);
+
+    let resumed_at = Instant::now();
+    let elapsed_before = state.elapsed;
+    loop {
+        state.elapsed = elapsed_before + resumed_at.elapsed();
+        println!("{}", tr!("guess.prompt", number = state.try_number + 1));
+        let time_limit = state.mode.time_limit(TIME_PER_GUESS, state.elapsed);
+        let input = read_game_line(time_limit);
+        state.elapsed = elapsed_before + resumed_at.elapsed();
+        let guess = match input {
+            GameInput::Line(guess) => guess,
+            GameInput::SaveAndQuit => return None,
+            GameInput::TimedOut if state.mode == Mode::Blitz => {
+                println!("{}", tr!("guess.times_up", secret = state.secret_number));
+                return Some(state.result(false));
+            }
+            GameInput::TimedOut => {
+                state.try_number += 1;
+                println!("{}", tr!("guess.too_slow"));
+                continue;
+            }
+        };
+        state.try_number += 1;
+
+        // This will crash the program if user enters a non-number
+        // let guess: u32 = guess.trim().parse().expect("Please type a number!");
+
+        // Rust allows us to shadow the previous value of 'guess' with a new one.
+        // This feature is often used in situations in which you want to convert a value from one
+        // type to another type.
+        // Because parse() can parse a variety of number types, we need to
+        // tell Rust the exact number type we want by using let guess: u32.
+        let guess: u32 = match guess.trim().parse() {
+            Ok(num) => num,
+            Err(_) => {
+                println!("{}", tr!("guess.wasted_try"));
+                continue;
+            }
+        };
+
+        // println!("You guessed: {}", guess);
+
+        match guess.cmp(&state.secret_number) {
+            Ordering::Less => {
+                println!("{}", tr!("guess.too_small"));
+                state.hints.push(Hint::TooSmall);
+            }
+            Ordering::Greater => {
+                println!("{}", tr!("guess.too_big"));
+                state.hints.push(Hint::TooBig);
+            }
+            Ordering::Equal => {
+                let seconds = format!("{:.1}", state.elapsed.as_secs_f64());
+                println!("{}", trn!("guess.won", state.try_number, seconds = seconds));
+                state.hints.push(Hint::Correct);
+                return Some(state.result(true));
+            }
+        }
+    }
+}
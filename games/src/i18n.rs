@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use lazy_static::lazy_static;
+
+// Looks up a message in the current locale, filling in any {name} placeholders:
+// tr!("menu.quit") or tr!("profiles.hello", name = player)
+macro_rules! tr {
+    ($key:expr) => {
+        $crate::i18n::message($key).to_string()
+    };
+    ($key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::format_message(
+            $crate::i18n::message($key),
+            &[$((stringify!($name), &$value as &dyn std::fmt::Display)),+],
+        )
+    };
+}
+
+// Like tr!, but for messages that depend on a count, which is also available as the {count} placeholder:
+// trn!("guess.won", tries, seconds = elapsed)
+macro_rules! trn {
+    ($key:expr, $count:expr $(, $name:ident = $value:expr)* $(,)?) => {{
+        let count = $count;
+        $crate::i18n::format_message(
+            $crate::i18n::plural_message($key, count as u64),
+            &[("count", &count as &dyn std::fmt::Display) $(, (stringify!($name), &$value as &dyn std::fmt::Display))*],
+        )
+    }};
+}
+
+// Every shipped locale, the first one is the default and the fallback for missing messages
+const BUNDLES: [(&str, &str); 2] = [
+    ("en", include_str!("../locales/en.txt")),
+    ("es", include_str!("../locales/es.txt")),
+];
+
+// Picked with the --lang flag, or else the GAMES_LANG or LANG environment variables
+pub(crate) const LANG_FLAG: &str = "--lang";
+
+lazy_static! {
+    static ref CATALOGS: Vec<HashMap<&'static str, &'static str>> =
+        BUNDLES.iter().map(|(_, source)| parse(source)).collect();
+}
+
+static CURRENT_LOCALE: AtomicUsize = AtomicUsize::new(0);
+
+// One "key = value" message per line, blank lines and lines starting with '#' are ignored
+fn parse(source: &'static str) -> HashMap<&'static str, &'static str> {
+    source.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => Some((key.trim(), value.trim())),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+// Accepts plain language codes as well as POSIX style locales, e.g. "es", "es-MX" or "es_ES.UTF-8"
+pub(crate) fn set_locale(locale: &str) -> bool {
+    let language = locale.split(&['_', '-', '.'][..]).next().unwrap_or_default();
+    match BUNDLES.iter().position(|(code, _)| code.eq_ignore_ascii_case(language)) {
+        Some(idx) => {
+            CURRENT_LOCALE.store(idx, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    }
+}
+
+pub(crate) fn select_locale(args: &[String]) {
+    let from_flag = args.iter()
+        .position(|arg| arg == LANG_FLAG)
+        .and_then(|idx| args.get(idx + 1))
+        .cloned();
+    let requested = from_flag
+        .or_else(|| env::var("GAMES_LANG").ok())
+        .or_else(|| env::var("LANG").ok());
+    if let Some(locale) = requested {
+        // unknown locales, like "C" or "POSIX", just leave the default in place
+        set_locale(&locale);
+    }
+}
+
+pub(crate) fn message(key: &str) -> &'static str {
+    let current = CURRENT_LOCALE.load(Ordering::Relaxed);
+    CATALOGS[current].get(key)
+        .or_else(|| CATALOGS[0].get(key))
+        .copied()
+        .unwrap_or_else(|| panic!("Missing message '{}'", key))
+}
+
+// Both English and Spanish only single out exactly one, everything else (including zero) is plural
+pub(crate) fn plural_message(key: &str, count: u64) -> &'static str {
+    let form = if count == 1 { "one" } else { "other" };
+    message(&format!("{}.{}", key, form))
+}
+
+pub(crate) fn format_message(template: &str, args: &[(&str, &dyn Display)]) -> String {
+    args.iter().fold(template.to_string(), |text, (name, value)| {
+        text.replace(&format!("{{{}}}", name), &value.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_key_exists_in_every_locale() {
+        let (default_code, _) = BUNDLES[0];
+        for ((code, _), catalog) in BUNDLES.iter().zip(CATALOGS.iter()).skip(1) {
+            for key in CATALOGS[0].keys() {
+                assert!(catalog.contains_key(key), "'{}' is missing from locale '{}'", key, code);
+            }
+            for key in catalog.keys() {
+                assert!(CATALOGS[0].contains_key(key), "'{}' in locale '{}' is missing from '{}'",
+                        key, code, default_code);
+            }
+        }
+    }
+
+    #[test]
+    fn plural_messages_have_both_forms() {
+        for ((code, _), catalog) in BUNDLES.iter().zip(CATALOGS.iter()) {
+            for key in catalog.keys() {
+                if let Some(base) = key.strip_suffix(".one") {
+                    assert!(catalog.contains_key(format!("{}.other", base).as_str()),
+                            "'{}.other' is missing from locale '{}'", base, code);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn placeholders_are_filled_in() {
+        let text = format_message("{name} won in {count} tries", &[("name", &"Ana"), ("count", &3)]);
+        assert_eq!(text, "Ana won in 3 tries");
+    }
+}
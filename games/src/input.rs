@@ -0,0 +1,221 @@
+use std::io::{stdin, stdout, Write};
+use std::process::exit;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+// Typing this at any prompt during a game saves it, so it can be resumed later from the menu
+pub(crate) const SAVE_COMMAND: &str = "save";
+
+pub(crate) enum GameInput {
+    Line(String),
+    SaveAndQuit,
+    TimedOut,
+}
+
+lazy_static! {
+    // stdin().read_line() cannot be interrupted, so a dedicated thread does all the reading and hands
+    // every line over a channel, which we can then wait on with a timeout.
+    // None means stdin was closed.
+    static ref LINES: Mutex<Receiver<Option<String>>> = {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || loop {
+            let mut input = String::new();
+            let bytes_read = stdin().read_line(&mut input).expect("Failed to read line");
+            let line = if bytes_read == 0 { None } else { Some(input) };
+            let closed = line.is_none();
+            if tx.send(line).is_err() || closed {
+                break;
+            }
+        });
+        Mutex::new(rx)
+    };
+}
+
+// Set when a timed read ran out of time. Whatever the player types after that was meant for the prompt they were
+// too slow for, and is thrown away before the next prompt rather than taken as the answer to it.
+static TIMED_OUT: AtomicBool = AtomicBool::new(false);
+
+// Throws away every line read so far, returning how many
+fn discard_pending(lines: &Receiver<Option<String>>) -> usize {
+    let mut discarded = 0;
+    // stopping at the end of input, which comes last: the reader is gone by then, so the channel still says so
+    while let Ok(Some(_)) = lines.try_recv() {
+        discarded += 1;
+    }
+    discarded
+}
+
+// Only lines typed after a timeout are stale, anything else (like input piped in) is still to be answered
+fn discard_late_lines(lines: &Receiver<Option<String>>) {
+    if TIMED_OUT.swap(false, Ordering::Relaxed) {
+        discard_pending(lines);
+    }
+}
+
+pub(crate) fn clear_screen() {
+    print!("{}[2J", 27 as char);
+    // print!("\x1B[2J");
+}
+
+fn received(line: Option<String>) -> String {
+    match line {
+        // remove the trailing new line character
+        Some(input) => input.trim_end_matches(&['\r', '\n'][..]).to_string(),
+        None => {
+            // end of input (e.g. Ctrl-D), nothing else will ever arrive
+            exit(0);
+        }
+    }
+}
+
+pub(crate) fn read_line() -> String {
+    let lines = LINES.lock().unwrap();
+    discard_late_lines(&lines);
+    let line = lines.recv().unwrap_or(None);
+    received(line)
+}
+
+// Shows how many seconds are left: every 10 seconds at first, then every second for the last 5
+fn should_announce(seconds_left: u64) -> bool {
+    seconds_left > 0 && (seconds_left <= 5 || seconds_left % 10 == 0)
+}
+
+// Like read_line, but gives up once the time limit runs out, showing a countdown in the meantime
+pub(crate) fn read_line_timeout(time_limit: Duration) -> Option<String> {
+    let lines = LINES.lock().unwrap();
+    discard_late_lines(&lines);
+    match wait_for_line(&lines, time_limit) {
+        Some(line) => Some(received(line)),
+        None => {
+            discard_pending(&lines);
+            TIMED_OUT.store(true, Ordering::Relaxed);
+            None
+        }
+    }
+}
+
+// The next line, or the end of input, unless the time limit runs out first
+fn wait_for_line(lines: &Receiver<Option<String>>, time_limit: Duration) -> Option<Option<String>> {
+    let deadline = Instant::now() + time_limit;
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return None;
+        }
+        let remaining = deadline - now;
+        // wake up on every whole second left, to update the countdown
+        let until_next_second = Duration::from_nanos(u64::from(remaining.subsec_nanos()));
+        let wait = if until_next_second == Duration::from_secs(0) {
+            Duration::from_secs(1)
+        } else {
+            until_next_second
+        };
+        match lines.recv_timeout(wait) {
+            Ok(line) => return Some(line),
+            Err(RecvTimeoutError::Timeout) => {
+                let seconds_left = deadline.saturating_duration_since(Instant::now()).as_secs_f64().round() as u64;
+                if should_announce(seconds_left) {
+                    print!("{} ", tr!("input.time_left", seconds = seconds_left));
+                    stdout().flush().unwrap();
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => return Some(None),
+        }
+    }
+}
+
+fn is_numeric(input: &str) -> bool {
+    let numbers_re = Regex::new(r"^([0-9]*)$").unwrap();
+    numbers_re.is_match(input)
+}
+
+pub(crate) fn read_numeric_input() -> String {
+    loop {
+        let input = read_line();
+        if is_numeric(&input) {
+            return input;
+        } else {
+            println!("{}", tr!("input.only_numbers"));
+        }
+    }
+}
+
+// Reads a line typed during a game, where the player can also save and quit, or run out of time
+pub(crate) fn read_game_line(time_limit: Option<Duration>) -> GameInput {
+    let input = match time_limit {
+        Some(time_limit) => match read_line_timeout(time_limit) {
+            Some(input) => input,
+            None => {
+                println!();
+                return GameInput::TimedOut;
+            }
+        },
+        None => read_line(),
+    };
+    if input.trim().eq_ignore_ascii_case(SAVE_COMMAND) {
+        GameInput::SaveAndQuit
+    } else {
+        GameInput::Line(input)
+    }
+}
+
+// Reads a game answer made only of the given (upper case) symbols. Lower case input is accepted too.
+pub(crate) fn read_game_input_in(symbols: &str, time_limit: Option<Duration>) -> GameInput {
+    let started = Instant::now();
+    loop {
+        // a mistyped answer does not buy the player more time
+        let time_left = time_limit.map(|limit| limit.checked_sub(started.elapsed()).unwrap_or_default());
+        match read_game_line(time_left) {
+            GameInput::Line(input) => {
+                let input = input.to_uppercase();
+                if input.chars().all(|c| symbols.contains(c)) {
+                    return GameInput::Line(input);
+                }
+                println!("{}", tr!("input.only_symbols", symbols = symbols));
+            }
+            other => return other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::Sender;
+
+    use super::*;
+
+    fn line(tx: &Sender<Option<String>>, text: &str) {
+        tx.send(Some(format!("{}\n", text))).unwrap();
+    }
+
+    #[test]
+    fn a_line_typed_too_late_is_not_taken_as_the_next_answer() {
+        let (tx, lines) = mpsc::channel();
+        line(&tx, "42");
+        assert_eq!(wait_for_line(&lines, Duration::from_millis(10)), Some(Some(String::from("42\n"))));
+        assert_eq!(wait_for_line(&lines, Duration::from_millis(10)), None);
+
+        // the answer to the prompt that timed out, and then the one to the next prompt
+        line(&tx, "9");
+        line(&tx, "9");
+        assert_eq!(discard_pending(&lines), 2);
+        line(&tx, "1");
+        assert_eq!(wait_for_line(&lines, Duration::from_millis(10)), Some(Some(String::from("1\n"))));
+    }
+
+    #[test]
+    fn the_end_of_input_is_never_discarded() {
+        let (tx, lines) = mpsc::channel();
+        line(&tx, "9");
+        tx.send(None).unwrap();
+        drop(tx);
+        assert_eq!(discard_pending(&lines), 1);
+        assert_eq!(wait_for_line(&lines, Duration::from_millis(10)), Some(None));
+    }
+}
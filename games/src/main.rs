@@ -1,141 +1,230 @@
-use std::cmp::Ordering;
-use std::io::stdin;
+use std::env;
+use std::io;
 use std::process::exit;
-use std::thread::sleep;
-use std::time::Duration;
 
-use rand::Rng;
-use regex::Regex;
+use crate::achievements::PlayerAchievements;
+use crate::adaptive::AdaptiveModel;
+use crate::daily::{daily_challenge, play_daily};
+use crate::game::{Game, GameState, Mode};
+use crate::input::{read_line, read_numeric_input};
+use crate::profiles::Profiles;
+use crate::savegame::{play_or_save, take_saved_game, SavedGame};
 
-fn clear_screen() {
-    print!("{}[2J", 27 as char);
-    // print!("\x1B[2J");
+// declared first, so its tr! and trn! macros can be used by all the other modules
+#[macro_use]
+mod i18n;
+
+mod achievements;
+mod adaptive;
+mod client;
+mod daily;
+mod game;
+mod guess_the_number;
+mod input;
+mod profiles;
+mod protocol;
+mod remember_numbers;
+mod savegame;
+mod server;
+mod stats;
+mod storage;
+
+#[derive(Clone, Copy)]
+enum MenuItem {
+    Play(Game),
+    Daily(Game),
+    Resume,
+    Statistics,
+    Achievements,
+    SwitchPlayer,
+    Quit,
 }
 
-fn read_numeric_input() -> String {
-    let numbers_re = Regex::new(r"^([0-9]*)$").unwrap();
-    loop {
-        let mut input = String::new();
-        stdin().read_line(&mut input).expect("Failed to read line");
-        // remove the trailing new line character
-        input.truncate(input.len() - 1);
-        if numbers_re.is_match(&input) {
-            return input;
-        } else {
-            println!("Only numbers allowed! Try again:");
+impl MenuItem {
+    fn label(self) -> String {
+        match self {
+            MenuItem::Play(Game::GuessTheNumber) => tr!("menu.guess_the_number"),
+            MenuItem::Play(Game::RememberNumbers) => tr!("menu.remember_numbers"),
+            MenuItem::Daily(Game::GuessTheNumber) => tr!("menu.daily_guess_the_number"),
+            MenuItem::Daily(Game::RememberNumbers) => tr!("menu.daily_remember_numbers"),
+            MenuItem::Resume => tr!("menu.resume"),
+            MenuItem::Statistics => tr!("menu.statistics"),
+            MenuItem::Achievements => tr!("menu.achievements"),
+            MenuItem::SwitchPlayer => tr!("menu.switch_player"),
+            MenuItem::Quit => tr!("menu.quit"),
         }
     }
 }
 
-fn remember_numbers() {
-    println!();
-    println!("Remember the numbers!");
-    println!();
+const MENU: [MenuItem; 9] = [
+    MenuItem::Play(Game::GuessTheNumber),
+    MenuItem::Play(Game::RememberNumbers),
+    MenuItem::Daily(Game::GuessTheNumber),
+    MenuItem::Daily(Game::RememberNumbers),
+    MenuItem::Resume,
+    MenuItem::Statistics,
+    MenuItem::Achievements,
+    MenuItem::SwitchPlayer,
+    MenuItem::Quit,
+];
 
-    // thread-local random number generator
-    let mut generator = rand::thread_rng();
+// Typed at the main menu, shows how the adaptive difficulty sees the current player
+const DEBUG_COMMAND: &str = "debug";
 
-    clear_screen();
-    let mut sequence = String::new();
-    loop {
-        if sequence.is_empty() {
-            println!("Please input your number (0 to 9): ");
-        } else {
-            println!("Please input the sequence so far, plus your new number (0 to 9): ");
+const MODES: [Mode; 3] = [Mode::Classic, Mode::Timed, Mode::Blitz];
+
+// Modes still locked behind an achievement are listed, but cannot be picked
+fn choose_mode(player: &str) -> Mode {
+    let achievements = PlayerAchievements::load(player);
+    let locked_by = |mode: Mode| achievements::required_for(mode).filter(|id| !achievements.is_unlocked(id));
+    println!();
+    println!("{}", tr!("menu.select_mode"));
+    println!();
+    for (idx, mode) in MODES.iter().enumerate() {
+        match locked_by(*mode) {
+            Some(id) => println!("{}. 🔒 {} {}", idx + 1, mode.label(), achievements::locked_hint(id)),
+            None => println!("{}. {}", idx + 1, mode.label()),
         }
-        let player_input = read_numeric_input();
-        let input_ok = if sequence.is_empty() {
-            true
-        } else {
-            player_input.starts_with(&sequence)
-        };
-        if input_ok {
-            if player_input.len() > sequence.len() {
-                sequence = player_input;
-                let computer_num = generator.gen_range(0, 10);
-                println!("Computer number: {}", computer_num);
-                // add to sequence
-                sequence.push_str(&computer_num.to_string());
-                sleep(Duration::from_millis(1000));
-                clear_screen();
-            } else {
-                println!("You need to add a new number to the sequence!");
-            }
-        } else {
-            println!("You lose!");
-            println!("The sequence was: '{}'. You remembered {} numbers correctly!",
-                     sequence, sequence.len() - 1);
-            break;
+    }
+    println!();
+    loop {
+        let choice: usize = read_numeric_input().trim().parse().unwrap_or(0);
+        match choice.checked_sub(1).and_then(|idx| MODES.get(idx)) {
+            Some(mode) => match locked_by(*mode) {
+                Some(id) => println!("{}", achievements::locked_hint(id)),
+                None => return *mode,
+            },
+            None => println!("{}", tr!("menu.invalid_choice", max = MODES.len())),
         }
     }
 }
 
-fn guess_the_number() {
-    println!();
-    println!("Guess the number!");
-    println!();
-
-    // thread-local random number generator
-    let mut generator = rand::thread_rng();
+// where `games serve` listens when no address is given, reachable from the whole LAN
+const DEFAULT_SERVER_ADDR: &str = "0.0.0.0:7878";
 
-    // the gen_range method is defined by the Rng trait that we brought into scope with the
-    // use rand::Rng statement
-    let secret_number = generator.gen_range(1, 101);
-    // println!("The secret number is: {}", secret_number);
-
-    let mut try_number: u32 = 0;
-    loop {
-        try_number += 1;
-        println!("Please input your guess (try #{}): ", try_number);
-        let mut guess = String::new(); // call "associated function", aka static method
-        stdin().read_line(&mut guess). // returns io::Result
-            expect("Failed to read line");
-
-        // This will crash the program if user enters a non-number
-        // let guess: u32 = guess.trim().parse().expect("Please type a number!");
-
-        // Rust allows us to shadow the previous value of 'guess' with a new one.
-        // This feature is often used in situations in which you want to convert a value from one
-        // type to another type.
-        // Because parse() can parse a variety of number types, we need to
-        // tell Rust the exact number type we want by using let guess: u32.
-        let guess: u32 = match guess.trim().parse() {
-            Ok(num) => num,
-            Err(_) => {
-                println!("YOU WASTED A TRY, DUMMY!");
-                continue;
-            }
-        };
+// The command line arguments, without the program name and the --lang flag
+fn command_args(args: &[String]) -> Vec<&str> {
+    let mut command = vec![];
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == i18n::LANG_FLAG {
+            iter.next();
+        } else {
+            command.push(arg.as_str());
+        }
+    }
+    command
+}
 
-        // println!("You guessed: {}", guess);
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    i18n::select_locale(&args);
 
-        match guess.cmp(&secret_number) {
-            Ordering::Less => println!("Too small!"),
-            Ordering::Greater => println!("Too big!"),
-            Ordering::Equal => {
-                println!("You won in {} tries!", try_number);
-                break;
+    let outcome = match command_args(&args).as_slice() {
+        [] => menu(),
+        ["serve"] => server::serve(DEFAULT_SERVER_ADDR),
+        ["serve", addr] => server::serve(addr),
+        ["join", addr] => {
+            let mut profiles = load_profiles();
+            if profiles.current_player().is_none() {
+                profiles.switch_player();
             }
+            client::join(addr, profiles.current_player().unwrap_or_default())
+        }
+        _ => {
+            println!("{}", tr!("usage"));
+            exit(2);
         }
+    };
+    if let Err(e) = outcome {
+        println!("{}", tr!("network_error", error = e));
+        exit(1);
     }
 }
 
-fn main() {
+// Profiles that cannot be read are left as they are, and the player's statistics are not recorded
+fn load_profiles() -> Profiles {
+    Profiles::load().unwrap_or_else(|e| {
+        println!("{}", tr!("profiles.load_error", error = e));
+        Profiles::unsaved()
+    })
+}
+
+fn menu() -> io::Result<()> {
+    let mut profiles = load_profiles();
+    if profiles.current_player().is_none() {
+        profiles.switch_player();
+    }
+
     loop {
+        let player = profiles.current_player().unwrap_or_default().to_string();
         println!();
-        println!("Select which game you want to play!");
+        println!("{}", tr!("menu.title", player = player));
         println!();
-        println!("1. Guess the number");
-        println!("2. Remember the numbers");
-        println!("3. Quit program");
+        for (idx, item) in MENU.iter().enumerate() {
+            println!("{}. {}", idx + 1, item.label());
+        }
         println!();
-        println!("Enter your choice:");
-        let choice: u32 = read_numeric_input().trim().parse().expect("Please type a number!");
-        match choice {
-            1 => guess_the_number(),
-            2 => remember_numbers(),
-            3 => exit(0),
-            _ => println!("Error. Please enter only 1, 2 or 3 !!!"),
+        println!("{}", tr!("menu.enter_choice"));
+        let choice = read_line();
+        if choice.trim() == DEBUG_COMMAND {
+            AdaptiveModel::load(&player).print_debug(&player);
+            continue;
+        }
+        let choice: usize = choice.trim().parse().unwrap_or(0);
+        let item = match choice.checked_sub(1).and_then(|idx| MENU.get(idx)) {
+            Some(item) => *item,
+            None => {
+                println!("{}", tr!("menu.invalid_choice", max = MENU.len()));
+                continue;
+            }
+        };
+        // only games played at the player's own difficulty feed back into it, the daily challenge is
+        // the same classic game for everybody
+        let mut adaptive = false;
+        let result = match item {
+            MenuItem::Play(game) => {
+                adaptive = true;
+                let difficulty = AdaptiveModel::load(&player).difficulty();
+                play_or_save(GameState::random(game, choose_mode(&player), difficulty), &player, None)
+            }
+            MenuItem::Daily(game) => daily_challenge(game, &player),
+            MenuItem::Resume => match take_saved_game(&player) {
+                Some(SavedGame { state, daily: Some(date) }) => play_daily(state, date, &player),
+                Some(SavedGame { state, daily: None }) => {
+                    adaptive = true;
+                    play_or_save(state, &player, None)
+                }
+                None => {
+                    println!("{}", tr!("menu.no_saved_game"));
+                    None
+                }
+            },
+            MenuItem::Statistics => {
+                if let Some(stats) = profiles.current_stats() {
+                    stats.print(&player);
+                }
+                None
+            }
+            MenuItem::Achievements => {
+                PlayerAchievements::load(&player).print();
+                None
+            }
+            MenuItem::SwitchPlayer => {
+                profiles.switch_player();
+                None
+            }
+            MenuItem::Quit => exit(0),
+        };
+        if let Some(result) = result {
+            profiles.record(&result);
+            let games_played = profiles.current_stats().map_or(0, |stats| stats.games_played());
+            achievements::evaluate(&player, &result, games_played);
+            if adaptive {
+                let mut model = AdaptiveModel::load(&player);
+                model.update(&result);
+                model.save(&player);
+            }
         }
     }
 }
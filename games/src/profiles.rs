@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::GameResult;
+use crate::input::read_line;
+use crate::stats::PlayerStats;
+use crate::storage;
+
+const PROFILES_FILE: &str = "profiles.json";
+
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct Profiles {
+    current: Option<String>,
+    players: BTreeMap<String, PlayerStats>,
+    // set when the profiles on file could not be read, so that they are never written over
+    #[serde(skip)]
+    unsaved: bool,
+}
+
+impl Profiles {
+    pub(crate) fn load() -> io::Result<Self> {
+        storage::load(PROFILES_FILE)
+    }
+
+    // Profiles that are never saved, for playing on without touching a file that could not be read
+    pub(crate) fn unsaved() -> Self {
+        Profiles {
+            unsaved: true,
+            ..Profiles::default()
+        }
+    }
+
+    pub(crate) fn save(&self) {
+        if self.unsaved {
+            return;
+        }
+        if let Err(e) = storage::save(PROFILES_FILE, self) {
+            println!("{}", tr!("profiles.save_error", error = e));
+        }
+    }
+
+    pub(crate) fn current_player(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    pub(crate) fn current_stats(&self) -> Option<&PlayerStats> {
+        self.current.as_ref().and_then(|name| self.players.get(name))
+    }
+
+    pub(crate) fn record(&mut self, result: &GameResult) {
+        if let Some(name) = &self.current {
+            self.players.entry(name.clone()).or_default().record(result);
+            self.save();
+        }
+    }
+
+    // Lets the player pick an existing profile by number, or create a new one by typing a name
+    pub(crate) fn switch_player(&mut self) {
+        println!();
+        println!("{}", tr!("profiles.who"));
+        println!();
+        let names: Vec<String> = self.players.keys().cloned().collect();
+        for (idx, name) in names.iter().enumerate() {
+            println!("{}. {}", idx + 1, name);
+        }
+        if !names.is_empty() {
+            println!();
+            println!("{}", tr!("profiles.pick"));
+        } else {
+            println!("{}", tr!("profiles.type_name"));
+        }
+        loop {
+            let input = read_line();
+            let input = input.trim();
+            let name = match input.parse::<usize>() {
+                Ok(idx) if idx >= 1 && idx <= names.len() => names[idx - 1].clone(),
+                _ => input.to_string(),
+            };
+            if name.is_empty() {
+                println!("{}", tr!("profiles.empty_name"));
+                continue;
+            }
+            println!("{}", tr!("profiles.hello", name = name));
+            self.players.entry(name.clone()).or_default();
+            self.current = Some(name);
+            self.save();
+            return;
+        }
+    }
+}
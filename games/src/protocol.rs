@@ -0,0 +1,133 @@
+use crate::game::Game;
+use crate::guess_the_number::Hint;
+
+// The multiplayer line protocol: every message is a single line of text, starting with an upper case
+// command followed by its space separated arguments. The server only ever tells clients how their moves
+// compare to the secret, never the secret itself (until the round is over).
+
+pub(crate) enum ClientMessage {
+    // the player's name, which may contain spaces but no commas
+    Hello(String),
+    Start(Game),
+    Guess(u32),
+    // the whole remember_numbers sequence so far
+    Sequence(String),
+    Quit,
+}
+
+pub(crate) enum ServerMessage {
+    Welcome(String),
+    // everybody connected, in joining order
+    Lobby(Vec<String>),
+    // the game and the number of players taking part; in guess_the_number races, also the highest number
+    Started(Game, usize, u32),
+    Hint(Hint),
+    // the next symbol every remember_numbers player has to add to the sequence
+    Show(char),
+    // a player got the remember_numbers sequence wrong
+    Out(String),
+    // the player and their score: tries for guess_the_number, numbers remembered for remember_numbers
+    Winner(String, u32),
+    // a round with no winner, e.g. every remember_numbers player went out at once
+    Draw(u32),
+    Error(String),
+}
+
+fn game_from(name: &str) -> Result<Game, String> {
+    match name {
+        "guess_the_number" => Ok(Game::GuessTheNumber),
+        "remember_numbers" => Ok(Game::RememberNumbers),
+        other => Err(format!("unknown game '{}'", other)),
+    }
+}
+
+fn number_from<T: std::str::FromStr>(text: &str) -> Result<T, String> {
+    text.parse().map_err(|_| format!("'{}' is not a number", text))
+}
+
+// splits "COMMAND the rest" into its two parts
+fn split_command(line: &str) -> (&str, &str) {
+    let line = line.trim_end_matches(&['\r', '\n'][..]);
+    let mut parts = line.splitn(2, ' ');
+    (parts.next().unwrap_or_default(), parts.next().unwrap_or_default())
+}
+
+impl ClientMessage {
+    pub(crate) fn to_line(&self) -> String {
+        match self {
+            ClientMessage::Hello(name) => format!("HELLO {}", name),
+            ClientMessage::Start(game) => format!("START {}", game.name()),
+            ClientMessage::Guess(guess) => format!("GUESS {}", guess),
+            ClientMessage::Sequence(sequence) => format!("SEQUENCE {}", sequence),
+            ClientMessage::Quit => "QUIT".to_string(),
+        }
+    }
+
+    pub(crate) fn parse(line: &str) -> Result<Self, String> {
+        let (command, rest) = split_command(line);
+        match command {
+            "HELLO" => Ok(ClientMessage::Hello(rest.trim().to_string())),
+            "START" => Ok(ClientMessage::Start(game_from(rest)?)),
+            "GUESS" => Ok(ClientMessage::Guess(number_from(rest)?)),
+            "SEQUENCE" => Ok(ClientMessage::Sequence(rest.to_string())),
+            "QUIT" => Ok(ClientMessage::Quit),
+            other => Err(format!("unknown command '{}'", other)),
+        }
+    }
+}
+
+impl ServerMessage {
+    pub(crate) fn to_line(&self) -> String {
+        match self {
+            ServerMessage::Welcome(name) => format!("WELCOME {}", name),
+            ServerMessage::Lobby(names) => format!("LOBBY {}", names.join(",")),
+            ServerMessage::Started(game, players, max_number) => {
+                format!("STARTED {} {} {}", game.name(), players, max_number)
+            }
+            ServerMessage::Hint(Hint::TooSmall) => "HINT too_small".to_string(),
+            ServerMessage::Hint(Hint::TooBig) => "HINT too_big".to_string(),
+            ServerMessage::Hint(Hint::Correct) => "HINT correct".to_string(),
+            ServerMessage::Show(symbol) => format!("SHOW {}", symbol),
+            ServerMessage::Out(name) => format!("OUT {}", name),
+            ServerMessage::Winner(name, score) => format!("WINNER {} {}", score, name),
+            ServerMessage::Draw(score) => format!("DRAW {}", score),
+            ServerMessage::Error(text) => format!("ERROR {}", text),
+        }
+    }
+
+    pub(crate) fn parse(line: &str) -> Result<Self, String> {
+        let (command, rest) = split_command(line);
+        match command {
+            "WELCOME" => Ok(ServerMessage::Welcome(rest.to_string())),
+            "LOBBY" if rest.is_empty() => Ok(ServerMessage::Lobby(vec![])),
+            "LOBBY" => Ok(ServerMessage::Lobby(rest.split(',').map(String::from).collect())),
+            "STARTED" => {
+                let args: Vec<&str> = rest.split(' ').collect();
+                match args.as_slice() {
+                    [game, players, max_number] => {
+                        Ok(ServerMessage::Started(game_from(game)?, number_from(players)?, number_from(max_number)?))
+                    }
+                    _ => Err(format!("bad STARTED arguments '{}'", rest)),
+                }
+            }
+            "HINT" => match rest {
+                "too_small" => Ok(ServerMessage::Hint(Hint::TooSmall)),
+                "too_big" => Ok(ServerMessage::Hint(Hint::TooBig)),
+                "correct" => Ok(ServerMessage::Hint(Hint::Correct)),
+                other => Err(format!("unknown hint '{}'", other)),
+            },
+            "SHOW" => rest.chars().next()
+                .map(ServerMessage::Show)
+                .ok_or_else(|| "SHOW without a symbol".to_string()),
+            "OUT" => Ok(ServerMessage::Out(rest.to_string())),
+            // the score goes first, as names may contain spaces
+            "WINNER" => {
+                let (score, name) = split_command(rest);
+                Ok(ServerMessage::Winner(name.to_string(), number_from(score)?))
+            }
+            "DRAW" => Ok(ServerMessage::Draw(number_from(rest)?)),
+            "ERROR" => Ok(ServerMessage::Error(rest.to_string())),
+            other => Err(format!("unknown command '{}'", other)),
+        }
+    }
+}
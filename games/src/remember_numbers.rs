@@ -0,0 +1,184 @@
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Difficulty, GameRng, Mode};
+use crate::input::{clear_screen, read_game_input_in, GameInput};
+
+// in timed mode the computer symbol stays on screen for this fraction of the usual time
+const TIMED_REVEAL_FACTOR: f64 = 0.6;
+
+// time to type the sequence back in timed mode: a base allowance plus some more for every number in it
+const BASE_ANSWER_TIME: Duration = Duration::from_secs(3);
+const ANSWER_TIME_PER_NUMBER: Duration = Duration::from_secs(1);
+
+// The symbols the computer picks from. Players can use any of them too.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum Alphabet {
+    Digits,
+    Hexadecimal,
+    Alphanumeric,
+}
+
+impl Alphabet {
+    pub(crate) fn symbols(self) -> &'static str {
+        match self {
+            Alphabet::Digits => "0123456789",
+            Alphabet::Hexadecimal => "0123456789ABCDEF",
+            Alphabet::Alphanumeric => "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+        }
+    }
+
+    fn last(self) -> char {
+        self.symbols().chars().last().unwrap()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct RememberState {
+    mode: Mode,
+    #[serde(default)]
+    difficulty: Difficulty,
+    generator: GameRng,
+    sequence: String,
+    // time spent playing, not counting the time the game was saved for
+    elapsed: Duration,
+}
+
+impl RememberState {
+    pub(crate) fn new(mode: Mode, difficulty: Difficulty, generator: GameRng) -> Self {
+        Self {
+            mode,
+            difficulty,
+            generator,
+            sequence: String::new(),
+            elapsed: Duration::from_secs(0),
+        }
+    }
+
+    fn reveal_time(&self) -> Duration {
+        match self.mode {
+            Mode::Timed => self.difficulty.reveal_time.mul_f64(TIMED_REVEAL_FACTOR),
+            Mode::Classic | Mode::Blitz => self.difficulty.reveal_time,
+        }
+    }
+
+    fn answer_time(&self) -> Duration {
+        BASE_ANSWER_TIME + ANSWER_TIME_PER_NUMBER * self.sequence.len() as u32
+    }
+
+    fn result(&self) -> RememberResult {
+        let remembered = self.sequence.len().saturating_sub(1);
+        let seconds = format!("{:.1}", self.elapsed.as_secs_f64());
+        println!("{}", trn!("remember.summary", remembered, sequence = self.sequence, seconds = seconds));
+        RememberResult {
+            mode: self.mode,
+            remembered,
+            elapsed: self.elapsed,
+        }
+    }
+}
+
+pub(crate) struct RememberResult {
+    pub(crate) mode: Mode,
+    pub(crate) remembered: usize,
+    pub(crate) elapsed: Duration,
+}
+
+// Returns None if the player chose to save and quit, in which case `state` holds the progress so far
+pub(crate) fn remember_numbers(state: &mut RememberState) -> Option<RememberResult> {
+    println!();
+    println!("{}", tr!("remember.title"));
+    println!();
+
+    clear_screen();
+    let alphabet = state.difficulty.alphabet;
+    let resumed_at = Instant::now();
+    let elapsed_before = state.elapsed;
+    loop {
+        state.elapsed = elapsed_before + resumed_at.elapsed();
+        if state.sequence.is_empty() {
+            println!("{}", tr!("remember.first_prompt", last = alphabet.last()));
+        } else {
+            println!("{}", tr!("remember.prompt", last = alphabet.last()));
+        }
+        let time_limit = state.mode.time_limit(state.answer_time(), state.elapsed);
+        let input = read_game_input_in(alphabet.symbols(), time_limit);
+        state.elapsed = elapsed_before + resumed_at.elapsed();
+        let player_input = match input {
+            GameInput::Line(input) => input,
+            GameInput::SaveAndQuit => return None,
+            GameInput::TimedOut => {
+                println!("{}", tr!("remember.too_slow"));
+                return Some(state.result());
+            }
+        };
+        let input_ok = if state.sequence.is_empty() {
+            true
+        } else {
+            player_input.starts_with(&state.sequence)
+        };
+        if input_ok {
+            if player_input.len() > state.sequence.len() {
+                state.sequence = player_input;
+                let symbols = alphabet.symbols();
+                let computer_idx = state.generator.gen_range(0, symbols.len());
+                let computer_symbol = &symbols[computer_idx..computer_idx + 1];
+                println!("{}", tr!("remember.computer_number", number = computer_symbol));
+                // add to sequence
+                state.sequence.push_str(computer_symbol);
+                sleep(state.reveal_time());
+                clear_screen();
+            } else {
+                println!("{}", tr!("remember.add_number"));
+            }
+        } else {
+            println!("{}", tr!("remember.lose"));
+            return Some(state.result());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use rand::SeedableRng;
+
+    use crate::game::GameState;
+    use crate::savegame::SavedGame;
+
+    use super::*;
+
+    fn next_symbols(generator: &mut GameRng) -> Vec<usize> {
+        (0..10).map(|_| generator.gen_range(0, Alphabet::Alphanumeric.symbols().len())).collect()
+    }
+
+    #[test]
+    fn a_resumed_game_carries_on_exactly_where_it_was_saved() {
+        let difficulty = Difficulty { alphabet: Alphabet::Alphanumeric, ..Difficulty::default() };
+        let mut state = RememberState::new(Mode::Timed, difficulty, GameRng::seed_from_u64(42));
+        // half way through, with some symbols drawn already
+        for _ in 0..4 {
+            let idx = state.generator.gen_range(0, Alphabet::Alphanumeric.symbols().len());
+            state.sequence.push_str(&Alphabet::Alphanumeric.symbols()[idx..idx + 1]);
+        }
+        state.elapsed = Duration::from_millis(12_345);
+        let saved = SavedGame { state: GameState::Remember(state), daily: NaiveDate::from_ymd_opt(2024, 3, 1) };
+
+        let json = serde_json::to_string(&saved).unwrap();
+        let resumed: SavedGame = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&resumed).unwrap(), json);
+        assert_eq!(resumed.daily, saved.daily);
+        match (saved.state, resumed.state) {
+            (GameState::Remember(mut original), GameState::Remember(mut resumed)) => {
+                assert_eq!(resumed.sequence, original.sequence);
+                assert_eq!(resumed.elapsed, original.elapsed);
+                assert!(resumed.mode == Mode::Timed && resumed.difficulty.alphabet == Alphabet::Alphanumeric);
+                assert_eq!(next_symbols(&mut resumed.generator), next_symbols(&mut original.generator));
+            }
+            _ => panic!("a remember_numbers game resumed as another game"),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::game::{GameResult, GameState};
+use crate::storage;
+
+const SAVES_FILE: &str = "saves.json";
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SavedGame {
+    pub(crate) state: GameState,
+    // set when the saved game is a daily challenge, so finishing it still counts as that day's attempt
+    pub(crate) daily: Option<NaiveDate>,
+}
+
+// Every player has a single save slot
+type SavedGames = BTreeMap<String, SavedGame>;
+
+// None when the saved games cannot be read, which leaves them untouched: nothing is stored over them
+fn load() -> Option<SavedGames> {
+    match storage::load(SAVES_FILE) {
+        Ok(saves) => Some(saves),
+        Err(e) => {
+            println!("{}", tr!("saves.load_error", error = e));
+            None
+        }
+    }
+}
+
+pub(crate) fn saved_game(player: &str) -> Option<SavedGame> {
+    load()?.remove(player)
+}
+
+// Removes the game from the player's slot, so a finished game cannot be resumed (and replayed) again
+pub(crate) fn take_saved_game(player: &str) -> Option<SavedGame> {
+    let mut saves = load()?;
+    let saved = saves.remove(player)?;
+    store(&saves);
+    Some(saved)
+}
+
+fn store(saves: &SavedGames) -> bool {
+    match storage::save(SAVES_FILE, saves) {
+        Ok(()) => true,
+        Err(e) => {
+            println!("{}", tr!("saves.save_error", error = e));
+            false
+        }
+    }
+}
+
+// Plays the game and, if the player asks to, saves it in their slot
+pub(crate) fn play_or_save(mut state: GameState, player: &str, daily: Option<NaiveDate>) -> Option<GameResult> {
+    match state.play() {
+        Some(result) => Some(result),
+        None => {
+            let mut saves = load()?;
+            if saves.insert(player.to_string(), SavedGame { state, daily }).is_some() {
+                println!("{}", tr!("saves.replaces"));
+            }
+            if store(&saves) {
+                println!("{}", tr!("saves.saved", resume = tr!("menu.resume")));
+            }
+            None
+        }
+    }
+}
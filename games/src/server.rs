@@ -0,0 +1,476 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use rand::Rng;
+
+use crate::game::Game;
+use crate::guess_the_number::Hint;
+use crate::protocol::{ClientMessage, ServerMessage};
+
+const MAX_NUMBER: u32 = 100;
+const MIN_PLAYERS: usize = 2;
+const DUEL_SYMBOLS: &str = "0123456789";
+
+// Everything the connection threads tell the game thread, which owns all the state
+enum Event {
+    Connected(usize, TcpStream),
+    Received(usize, ClientMessage),
+    Invalid(usize, String),
+    Disconnected(usize),
+}
+
+struct Connection {
+    // None until the client has said HELLO
+    name: Option<String>,
+    stream: TcpStream,
+}
+
+enum Round {
+    Lobby,
+    GuessRace {
+        secret_number: u32,
+        // player -> tries so far
+        tries: BTreeMap<usize, u32>,
+    },
+    RememberDuel {
+        sequence: String,
+        // players still in, and those of them who already typed the current sequence
+        active: BTreeSet<usize>,
+        answered: BTreeSet<usize>,
+    },
+}
+
+struct Server {
+    // connection ids are handed out in order, so this also keeps players in joining order
+    connections: BTreeMap<usize, Connection>,
+    round: Round,
+}
+
+pub(crate) fn serve(addr: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    println!("{}", tr!("server.listening", addr = listener.local_addr()?));
+    run(listener);
+    Ok(())
+}
+
+// Accepts players on one thread, reads from every player on a thread of its own, and runs the game on
+// the calling thread, which is the only one that ever knows the secrets
+pub(crate) fn run(listener: TcpListener) {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for (id, stream) in listener.incoming().enumerate() {
+            match stream {
+                Ok(stream) => {
+                    let tx = tx.clone();
+                    thread::spawn(move || read_messages(id, stream, tx));
+                }
+                Err(e) => println!("{}", tr!("server.accept_error", error = e)),
+            }
+        }
+    });
+
+    let mut server = Server {
+        connections: BTreeMap::new(),
+        round: Round::Lobby,
+    };
+    for event in rx {
+        server.handle(event);
+    }
+}
+
+fn read_messages(id: usize, stream: TcpStream, tx: Sender<Event>) {
+    let reader = match stream.try_clone() {
+        Ok(reader) => BufReader::new(reader),
+        Err(_) => return,
+    };
+    if tx.send(Event::Connected(id, stream)).is_err() {
+        return;
+    }
+    for line in reader.lines() {
+        let event = match line {
+            Ok(line) => match ClientMessage::parse(&line) {
+                Ok(message) => Event::Received(id, message),
+                Err(e) => Event::Invalid(id, e),
+            },
+            Err(_) => break,
+        };
+        if tx.send(event).is_err() {
+            return;
+        }
+    }
+    let _ = tx.send(Event::Disconnected(id));
+}
+
+impl Server {
+    fn send(&mut self, id: usize, message: &ServerMessage) {
+        if let Some(connection) = self.connections.get_mut(&id) {
+            // a failed write means the client is gone, which its reading thread will report
+            let _ = writeln!(connection.stream, "{}", message.to_line());
+        }
+    }
+
+    fn broadcast(&mut self, message: &ServerMessage) {
+        let ids: Vec<usize> = self.connections.keys().copied().collect();
+        for id in ids {
+            self.send(id, message);
+        }
+    }
+
+    fn name(&self, id: usize) -> String {
+        self.connections.get(&id).and_then(|c| c.name.clone()).unwrap_or_default()
+    }
+
+    fn players(&self) -> Vec<usize> {
+        self.connections.iter().filter(|(_, c)| c.name.is_some()).map(|(&id, _)| id).collect()
+    }
+
+    fn broadcast_lobby(&mut self) {
+        let names = self.players().into_iter().map(|id| self.name(id)).collect();
+        self.broadcast(&ServerMessage::Lobby(names));
+    }
+
+    fn end_round(&mut self, outcome: ServerMessage) {
+        self.round = Round::Lobby;
+        self.broadcast(&outcome);
+        self.broadcast_lobby();
+    }
+
+    fn handle(&mut self, event: Event) {
+        match event {
+            Event::Connected(id, stream) => {
+                self.connections.insert(id, Connection { name: None, stream });
+            }
+            Event::Invalid(id, e) => self.send(id, &ServerMessage::Error(e)),
+            Event::Disconnected(id) => self.leave(id),
+            Event::Received(id, ClientMessage::Hello(name)) => self.hello(id, name),
+            Event::Received(id, _) if self.connections.get(&id).map_or(true, |c| c.name.is_none()) => {
+                self.send(id, &ServerMessage::Error("say HELLO first".to_string()));
+            }
+            Event::Received(id, ClientMessage::Start(game)) => self.start(id, game),
+            Event::Received(id, ClientMessage::Guess(guess)) => self.guess(id, guess),
+            Event::Received(id, ClientMessage::Sequence(sequence)) => self.sequence(id, &sequence),
+            Event::Received(id, ClientMessage::Quit) => {
+                if let Some(connection) = self.connections.get(&id) {
+                    let _ = connection.stream.shutdown(Shutdown::Both);
+                }
+                self.leave(id);
+            }
+        }
+    }
+
+    fn hello(&mut self, id: usize, name: String) {
+        let error = if name.is_empty() || name.contains(',') {
+            Some("names cannot be empty or contain commas")
+        } else if self.connections.get(&id).is_some_and(|c| c.name.is_some()) {
+            Some("you already said HELLO")
+        } else if self.connections.values().any(|c| c.name.as_ref() == Some(&name)) {
+            Some("that name is taken")
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            self.send(id, &ServerMessage::Error(error.to_string()));
+            return;
+        }
+        println!("{}", tr!("server.joined", name = name));
+        if let Some(connection) = self.connections.get_mut(&id) {
+            connection.name = Some(name.clone());
+        }
+        self.send(id, &ServerMessage::Welcome(name));
+        self.broadcast_lobby();
+    }
+
+    fn leave(&mut self, id: usize) {
+        let connection = match self.connections.remove(&id) {
+            Some(connection) => connection,
+            None => return,
+        };
+        let name = match connection.name {
+            Some(name) => name,
+            None => return,
+        };
+        println!("{}", tr!("server.left", name = name));
+        match &mut self.round {
+            Round::Lobby => {}
+            Round::GuessRace { tries, .. } => {
+                tries.remove(&id);
+                if tries.is_empty() {
+                    self.round = Round::Lobby;
+                }
+            }
+            Round::RememberDuel { active, .. } => {
+                if active.contains(&id) {
+                    self.broadcast(&ServerMessage::Out(name));
+                    self.knock_out(id);
+                    // a duel that ended with it has shown everybody the lobby already
+                    if matches!(self.round, Round::Lobby) {
+                        return;
+                    }
+                }
+            }
+        }
+        self.broadcast_lobby();
+    }
+
+    fn start(&mut self, id: usize, game: Game) {
+        if !matches!(self.round, Round::Lobby) {
+            self.send(id, &ServerMessage::Error("a round is already being played".to_string()));
+            return;
+        }
+        let players = self.players();
+        if players.len() < MIN_PLAYERS {
+            self.send(id, &ServerMessage::Error(format!("at least {} players are needed", MIN_PLAYERS)));
+            return;
+        }
+        let mut generator = rand::thread_rng();
+        match game {
+            Game::GuessTheNumber => {
+                self.round = Round::GuessRace {
+                    secret_number: generator.gen_range(1, MAX_NUMBER + 1),
+                    tries: players.iter().map(|&id| (id, 0)).collect(),
+                };
+                self.broadcast(&ServerMessage::Started(game, players.len(), MAX_NUMBER));
+            }
+            Game::RememberNumbers => {
+                let symbol = random_symbol(&mut generator);
+                self.round = Round::RememberDuel {
+                    sequence: symbol.to_string(),
+                    active: players.iter().copied().collect(),
+                    answered: BTreeSet::new(),
+                };
+                self.broadcast(&ServerMessage::Started(game, players.len(), 0));
+                self.broadcast(&ServerMessage::Show(symbol));
+            }
+        }
+    }
+
+    fn guess(&mut self, id: usize, guess: u32) {
+        let (secret_number, tries) = match &mut self.round {
+            Round::GuessRace { secret_number, tries } => match tries.get_mut(&id) {
+                Some(tries) => (*secret_number, tries),
+                None => {
+                    self.send(id, &ServerMessage::Error("you are not in this race".to_string()));
+                    return;
+                }
+            },
+            _ => {
+                self.send(id, &ServerMessage::Error("no guess_the_number race is being played".to_string()));
+                return;
+            }
+        };
+        if !(1..=MAX_NUMBER).contains(&guess) {
+            self.send(id, &ServerMessage::Error(format!("guesses go from 1 to {}", MAX_NUMBER)));
+            return;
+        }
+        *tries += 1;
+        let tries = *tries;
+        let hint = match guess.cmp(&secret_number) {
+            std::cmp::Ordering::Less => Hint::TooSmall,
+            std::cmp::Ordering::Greater => Hint::TooBig,
+            std::cmp::Ordering::Equal => Hint::Correct,
+        };
+        self.send(id, &ServerMessage::Hint(hint));
+        if let Hint::Correct = hint {
+            let winner = self.name(id);
+            self.end_round(ServerMessage::Winner(winner, tries));
+        }
+    }
+
+    fn sequence(&mut self, id: usize, typed: &str) {
+        let correct = match &mut self.round {
+            Round::RememberDuel { sequence, active, answered } if active.contains(&id) => {
+                if answered.contains(&id) {
+                    self.send(id, &ServerMessage::Error("wait for the next symbol".to_string()));
+                    return;
+                }
+                typed.trim().eq_ignore_ascii_case(sequence)
+            }
+            _ => {
+                self.send(id, &ServerMessage::Error("you are not in a remember_numbers duel".to_string()));
+                return;
+            }
+        };
+        if correct {
+            if let Round::RememberDuel { answered, .. } = &mut self.round {
+                answered.insert(id);
+            }
+            self.send(id, &ServerMessage::Hint(Hint::Correct));
+            self.next_symbol_if_all_answered();
+        } else {
+            let name = self.name(id);
+            self.broadcast(&ServerMessage::Out(name));
+            self.knock_out(id);
+        }
+    }
+
+    // Takes a player out of the duel, which ends when at most one is left standing
+    fn knock_out(&mut self, id: usize) {
+        let (remembered, outcome) = match &mut self.round {
+            Round::RememberDuel { sequence, active, answered } => {
+                active.remove(&id);
+                answered.remove(&id);
+                // the current sequence counts for those who already typed it
+                let remembered = sequence.len() as u32 - 1;
+                match active.iter().next() {
+                    None => (remembered, None),
+                    Some(&last) if active.len() == 1 => {
+                        let score = if answered.contains(&last) { remembered + 1 } else { remembered };
+                        (score, Some(last))
+                    }
+                    Some(_) => {
+                        self.next_symbol_if_all_answered();
+                        return;
+                    }
+                }
+            }
+            _ => return,
+        };
+        match outcome {
+            Some(winner) => {
+                let name = self.name(winner);
+                self.end_round(ServerMessage::Winner(name, remembered));
+            }
+            None => self.end_round(ServerMessage::Draw(remembered)),
+        }
+    }
+
+    fn next_symbol_if_all_answered(&mut self) {
+        let symbol = match &mut self.round {
+            Round::RememberDuel { sequence, active, answered } if answered == active => {
+                let symbol = random_symbol(&mut rand::thread_rng());
+                sequence.push(symbol);
+                answered.clear();
+                symbol
+            }
+            _ => return,
+        };
+        self.broadcast(&ServerMessage::Show(symbol));
+    }
+}
+
+fn random_symbol<R: Rng>(generator: &mut R) -> char {
+    let idx = generator.gen_range(0, DUEL_SYMBOLS.len());
+    DUEL_SYMBOLS.as_bytes()[idx] as char
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    struct TestClient {
+        reader: BufReader<TcpStream>,
+        writer: TcpStream,
+    }
+
+    impl TestClient {
+        fn connect(addr: &str, name: &str) -> Self {
+            let writer = TcpStream::connect(addr).unwrap();
+            writer.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            let mut client = TestClient {
+                reader: BufReader::new(writer.try_clone().unwrap()),
+                writer,
+            };
+            client.send(ClientMessage::Hello(name.to_string()));
+            client.receive_until(|line| line.starts_with("WELCOME"));
+            client
+        }
+
+        fn send(&mut self, message: ClientMessage) {
+            writeln!(self.writer, "{}", message.to_line()).unwrap();
+        }
+
+        // skips messages (e.g. lobby updates) until one matches
+        fn receive_until<F: Fn(&str) -> bool>(&mut self, matches: F) -> String {
+            loop {
+                let mut line = String::new();
+                assert!(self.reader.read_line(&mut line).unwrap() > 0, "server hung up");
+                let line = line.trim_end().to_string();
+                ServerMessage::parse(&line).unwrap();
+                if matches(&line) {
+                    return line;
+                }
+            }
+        }
+    }
+
+    fn start_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || run(listener));
+        addr
+    }
+
+    #[test]
+    fn guess_race_never_reveals_the_secret() {
+        let addr = start_server();
+        let mut alice = TestClient::connect(&addr, "alice");
+        let mut bob = TestClient::connect(&addr, "bob");
+        alice.receive_until(|line| line == "LOBBY alice,bob");
+
+        alice.send(ClientMessage::Start(Game::GuessTheNumber));
+        assert_eq!(alice.receive_until(|line| line.starts_with("STARTED")), "STARTED guess_the_number 2 100");
+        bob.receive_until(|line| line.starts_with("STARTED"));
+
+        bob.send(ClientMessage::Guess(MAX_NUMBER + 1));
+        bob.receive_until(|line| line.starts_with("ERROR"));
+
+        // alice finds the number with a binary search, only ever getting hints back
+        let (mut low, mut high) = (1, MAX_NUMBER);
+        let mut tries = 0;
+        loop {
+            let guess = (low + high) / 2;
+            tries += 1;
+            alice.send(ClientMessage::Guess(guess));
+            match alice.receive_until(|line| line.starts_with("HINT")).as_str() {
+                "HINT too_small" => low = guess + 1,
+                "HINT too_big" => high = guess - 1,
+                "HINT correct" => break,
+                other => panic!("unexpected {}", other),
+            }
+        }
+        let winner = format!("WINNER {} alice", tries);
+        assert_eq!(bob.receive_until(|line| line.starts_with("WINNER")), winner);
+    }
+
+    #[test]
+    fn remember_duel_knocks_out_wrong_sequences() {
+        let addr = start_server();
+        let mut alice = TestClient::connect(&addr, "alice");
+        let mut bob = TestClient::connect(&addr, "bob");
+        alice.receive_until(|line| line == "LOBBY alice,bob");
+
+        bob.send(ClientMessage::Start(Game::RememberNumbers));
+        let shown = alice.receive_until(|line| line.starts_with("SHOW"));
+        let symbol = shown.trim_start_matches("SHOW ");
+        bob.receive_until(|line| line.starts_with("SHOW"));
+
+        alice.send(ClientMessage::Sequence(symbol.to_string()));
+        alice.receive_until(|line| line == "HINT correct");
+        bob.send(ClientMessage::Sequence("not it".to_string()));
+        assert_eq!(alice.receive_until(|line| line.starts_with("OUT")), "OUT bob");
+        assert_eq!(bob.receive_until(|line| line.starts_with("WINNER")), "WINNER 1 alice");
+    }
+
+    #[test]
+    fn everybody_left_in_a_duel_sees_who_is_still_around() {
+        let addr = start_server();
+        let mut alice = TestClient::connect(&addr, "alice");
+        let mut bob = TestClient::connect(&addr, "bob");
+        let mut carol = TestClient::connect(&addr, "carol");
+        alice.receive_until(|line| line == "LOBBY alice,bob,carol");
+        bob.receive_until(|line| line == "LOBBY alice,bob,carol");
+
+        alice.send(ClientMessage::Start(Game::RememberNumbers));
+        carol.receive_until(|line| line.starts_with("SHOW"));
+        carol.send(ClientMessage::Quit);
+        // the duel goes on without carol, who is gone from the lobby too
+        assert_eq!(alice.receive_until(|line| line.starts_with("OUT")), "OUT carol");
+        alice.receive_until(|line| line == "LOBBY alice,bob");
+        bob.receive_until(|line| line == "LOBBY alice,bob");
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::GameResult;
+
+// remembering at least this many numbers counts as a win, and keeps the streak going
+pub(crate) const REMEMBER_WIN_THRESHOLD: usize = 5;
+
+const CHART_WIDTH: u32 = 30;
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct GuessStats {
+    pub(crate) games_played: u32,
+    pub(crate) wins: u32,
+    pub(crate) total_tries: u32,
+    pub(crate) best_tries: Option<u32>,
+    pub(crate) fastest_win: Option<Duration>,
+    // number of tries -> number of games won with that many tries
+    pub(crate) distribution: BTreeMap<u32, u32>,
+}
+
+impl GuessStats {
+    fn average_tries(&self) -> Option<f64> {
+        if self.wins == 0 {
+            None
+        } else {
+            Some(f64::from(self.total_tries) / f64::from(self.wins))
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct RememberStats {
+    pub(crate) games_played: u32,
+    pub(crate) wins: u32,
+    pub(crate) longest_sequence: usize,
+    pub(crate) current_streak: u32,
+    pub(crate) best_streak: u32,
+    // numbers remembered -> number of games
+    pub(crate) distribution: BTreeMap<usize, u32>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct PlayerStats {
+    pub(crate) guess: GuessStats,
+    pub(crate) remember: RememberStats,
+}
+
+impl PlayerStats {
+    pub(crate) fn games_played(&self) -> u32 {
+        self.guess.games_played + self.remember.games_played
+    }
+
+    pub(crate) fn record(&mut self, result: &GameResult) {
+        match result {
+            GameResult::Guess(guess) => {
+                let stats = &mut self.guess;
+                stats.games_played += 1;
+                if result.won() {
+                    stats.wins += 1;
+                    stats.total_tries += guess.tries;
+                    stats.best_tries = Some(stats.best_tries.map_or(guess.tries, |best| best.min(guess.tries)));
+                    stats.fastest_win = Some(stats.fastest_win.map_or(guess.elapsed, |best| best.min(guess.elapsed)));
+                    *stats.distribution.entry(guess.tries).or_insert(0) += 1;
+                }
+            }
+            GameResult::Remember(remember) => {
+                let stats = &mut self.remember;
+                stats.games_played += 1;
+                if result.won() {
+                    stats.wins += 1;
+                    stats.current_streak += 1;
+                    stats.best_streak = stats.best_streak.max(stats.current_streak);
+                } else {
+                    stats.current_streak = 0;
+                }
+                stats.longest_sequence = stats.longest_sequence.max(remember.remembered);
+                *stats.distribution.entry(remember.remembered).or_insert(0) += 1;
+            }
+        }
+    }
+
+    pub(crate) fn print(&self, player: &str) {
+        println!();
+        println!("{}", tr!("stats.title", player = player));
+        println!();
+
+        let guess = &self.guess;
+        println!("{}", tr!("stats.guess_the_number"));
+        println!("  {}", tr!("stats.games_played", count = guess.games_played));
+        println!("  {}", tr!("stats.wins", count = guess.wins));
+        match (guess.average_tries(), guess.best_tries) {
+            (Some(average), Some(best)) => {
+                println!("  {}", tr!("stats.average_tries", average = format!("{:.1}", average)));
+                println!("  {}", tr!("stats.best_tries", count = best));
+            }
+            _ => println!("  {}", tr!("stats.average_tries", average = "-")),
+        }
+        if let Some(fastest) = guess.fastest_win {
+            println!("  {}", tr!("stats.fastest_win", seconds = format!("{:.1}", fastest.as_secs_f64())));
+        }
+        print_distribution(&tr!("stats.tries_distribution"), &guess.distribution);
+
+        let remember = &self.remember;
+        println!();
+        println!("{}", tr!("stats.remember_numbers"));
+        println!("  {}", tr!("stats.games_played", count = remember.games_played));
+        println!("  {}", tr!("stats.remember_wins", threshold = REMEMBER_WIN_THRESHOLD, count = remember.wins));
+        println!("  {}", tr!("stats.longest_sequence", count = remember.longest_sequence));
+        println!("  {}", tr!("stats.current_streak", count = remember.current_streak));
+        println!("  {}", tr!("stats.best_streak", count = remember.best_streak));
+        print_distribution(&tr!("stats.numbers_distribution"), &remember.distribution);
+    }
+}
+
+// Renders a horizontal bar chart, scaled so that the most frequent bucket is CHART_WIDTH wide
+fn print_distribution<K: ToString>(title: &str, distribution: &BTreeMap<K, u32>) {
+    let max = match distribution.values().max() {
+        Some(&max) => max,
+        None => return,
+    };
+    println!();
+    println!("  {}", title);
+    let labels: Vec<String> = distribution.keys().map(|k| k.to_string()).collect();
+    let label_width = labels.iter().map(|l| l.len()).max().unwrap_or(0);
+    for (key, count) in labels.iter().zip(distribution.values()) {
+        // always draw at least one '#' so rare buckets stay visible
+        let bar_len = (count * CHART_WIDTH / max).max(1) as usize;
+        println!("  {:>width$} | {} {}", key, "#".repeat(bar_len), count, width = label_width + 2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::Mode;
+    use crate::guess_the_number::GuessResult;
+    use crate::remember_numbers::RememberResult;
+
+    use super::*;
+
+    fn guess(won: bool, tries: u32, seconds: u64) -> GameResult {
+        GameResult::Guess(GuessResult {
+            mode: Mode::Classic,
+            won,
+            max_number: 100,
+            tries,
+            hints: vec![],
+            elapsed: Duration::from_secs(seconds),
+        })
+    }
+
+    fn remember(remembered: usize) -> GameResult {
+        GameResult::Remember(RememberResult { mode: Mode::Classic, remembered, elapsed: Duration::from_secs(10) })
+    }
+
+    #[test]
+    fn only_won_guessing_games_count_towards_tries() {
+        let mut stats = PlayerStats::default();
+        for result in &[guess(true, 7, 30), guess(true, 4, 45), guess(false, 12, 60), guess(true, 7, 20)] {
+            stats.record(result);
+        }
+        let guess = &stats.guess;
+        assert_eq!((guess.games_played, guess.wins, guess.total_tries), (4, 3, 18));
+        assert_eq!(guess.best_tries, Some(4));
+        assert_eq!(guess.fastest_win, Some(Duration::from_secs(20)));
+        assert_eq!(guess.average_tries(), Some(6.0));
+        assert_eq!(guess.distribution.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>(), vec![(4, 1), (7, 2)]);
+        assert_eq!(stats.games_played(), 4);
+    }
+
+    #[test]
+    fn a_remembering_streak_ends_below_the_threshold() {
+        let mut stats = PlayerStats::default();
+        for remembered in &[REMEMBER_WIN_THRESHOLD, 8, 2, REMEMBER_WIN_THRESHOLD] {
+            stats.record(&remember(*remembered));
+        }
+        let remember = &stats.remember;
+        assert_eq!((remember.games_played, remember.wins), (4, 3));
+        assert_eq!((remember.current_streak, remember.best_streak), (1, 2));
+        assert_eq!(remember.longest_sequence, 8);
+        // lost games are in the distribution too
+        assert_eq!(remember.distribution.get(&2), Some(&1));
+        assert_eq!(remember.distribution.get(&REMEMBER_WIN_THRESHOLD), Some(&2));
+        assert_eq!(stats.guess.average_tries(), None);
+    }
+}
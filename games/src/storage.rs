@@ -0,0 +1,41 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// Everything the games remember between runs lives in a single directory:
+// $GAMES_DATA_DIR if set, otherwise ~/.rusty-games
+pub(crate) fn data_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("GAMES_DATA_DIR") {
+        return PathBuf::from(dir);
+    }
+    match env::var_os("HOME") {
+        Some(home) => PathBuf::from(home).join(".rusty-games"),
+        None => PathBuf::from(".rusty-games"),
+    }
+}
+
+// A missing file is not an error, it just means nothing has been stored yet
+pub(crate) fn load<T: DeserializeOwned + Default>(file_name: &str) -> io::Result<T> {
+    let path = data_dir().join(file_name);
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(T::default()),
+        Err(e) => return Err(e),
+    };
+    serde_json::from_str(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub(crate) fn save<T: Serialize>(file_name: &str, value: &T) -> io::Result<()> {
+    let dir = data_dir();
+    fs::create_dir_all(&dir)?;
+    let contents = serde_json::to_string_pretty(value)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    // write to a temporary file first so a crash never leaves a half written file behind
+    let tmp_path = dir.join(format!("{}.tmp", file_name));
+    fs::write(&tmp_path, contents)?;
+    fs::rename(tmp_path, dir.join(file_name))
+}